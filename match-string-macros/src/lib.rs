@@ -1,5 +1,5 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned};
 use syn::parse::{Parse, ParseStream};
 use syn::{Expr, Ident, Token, parse_macro_input};
 
@@ -7,6 +7,16 @@ struct MatchesInput {
     reference: Expr,
     _arrow: Token![=>],
     pattern: PatternExpr,
+    /// Block run when the match fails, written as `; else { ... }` after the pattern.
+    else_branch: Option<syn::Block>,
+}
+
+/// Input of `match_or!`: `reference => pattern, fallback`. The fallback is everything
+/// after the last top-level comma, since commas also separate the pattern's terms.
+struct MatchOrInput {
+    reference: Expr,
+    pattern: PatternExpr,
+    fallback: Expr,
 }
 
 struct PatternExpr {
@@ -20,9 +30,65 @@ enum PatternKind {
     Or(Vec<PatternExpr>),
     Many(Box<PatternExpr>),
     Some(Box<PatternExpr>),
+    /// `p?`: `p` if it matches, nothing otherwise.
+    Opt(Box<PatternExpr>),
+    /// `&p`: succeeds where `p` would match, without consuming anything.
+    Lookahead(Box<PatternExpr>),
+    /// `!p`: succeeds where `p` would not match, without consuming anything.
+    NegLookahead(Box<PatternExpr>),
     Sep(Box<PatternExpr>, Box<PatternExpr>),
     Sep1(Box<PatternExpr>, Box<PatternExpr>),
+    /// `sep_trailing!(elem, sep)`: every element followed by a separator.
+    SepTerminated(Box<PatternExpr>, Box<PatternExpr>),
     To(Ident, Box<PatternExpr>),
+    AnyOf(Vec<syn::Lit>),
+    NoneOf(Vec<syn::Lit>),
+    Rest,
+    /// The `$` end-of-input anchor.
+    End,
+    /// A character range such as `'a'..='z'`; the flag marks an inclusive end.
+    Range(syn::Lit, syn::Lit, bool),
+    /// A string literal written while the `(?i)` flag is on.
+    CaseInsensitive(Box<PatternExpr>),
+    /// A bounded repetition `p{min..=max}` or `p{min,max}`; an exclusive `p{min..end}` is
+    /// stored as `min..=end - 1`, and an exact count `p{n}` as `n..=n`.
+    Repeat(Box<PatternExpr>, usize, usize),
+    /// `fmt_pat!(...)`: the text of a `format_args!` call with these arguments.
+    Format(proc_macro2::TokenStream),
+}
+
+/// Mode flags in effect while parsing, set by `(?i)` and cleared by `(?-i)`. A flag set
+/// inside a group lasts until the end of that group.
+#[derive(Clone, Copy, Default)]
+struct ParseContext {
+    case_insensitive: bool,
+}
+
+struct CompilePatternInput {
+    vis: syn::Visibility,
+    name: Ident,
+    ty: syn::Type,
+    pattern: Expr,
+}
+
+impl Parse for CompilePatternInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let vis: syn::Visibility = input.parse()?;
+        let name: Ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let ty: syn::Type = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let pattern: Expr = input.parse()?;
+        if input.peek(Token![;]) {
+            input.parse::<Token![;]>()?;
+        }
+        Ok(CompilePatternInput {
+            vis,
+            name,
+            ty,
+            pattern,
+        })
+    }
 }
 
 impl Parse for MatchesInput {
@@ -30,24 +96,62 @@ impl Parse for MatchesInput {
         let reference: Expr = input.parse()?;
         let _arrow: Token![=>] = input.parse()?;
         let pattern = input.parse::<PatternExpr>()?;
+        let else_branch = if input.peek(Token![;]) {
+            input.parse::<Token![;]>()?;
+            input.parse::<Token![else]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
         Ok(MatchesInput {
             reference,
             _arrow,
             pattern,
+            else_branch,
+        })
+    }
+}
+
+impl Parse for MatchOrInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let reference: Expr = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let rest: Vec<proc_macro2::TokenTree> = input
+            .parse::<proc_macro2::TokenStream>()?
+            .into_iter()
+            .collect();
+        let split = rest
+            .iter()
+            .rposition(
+                |tt| std::matches!(tt, proc_macro2::TokenTree::Punct(p) if p.as_char() == ','),
+            )
+            .ok_or_else(|| input.error("expected `, fallback` after the pattern"))?;
+        let pattern = syn::parse2(rest[..split].iter().cloned().collect())?;
+        let fallback = syn::parse2(rest[split + 1..].iter().cloned().collect())?;
+        Ok(MatchOrInput {
+            reference,
+            pattern,
+            fallback,
         })
     }
 }
 
 impl Parse for PatternExpr {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        parse_seq_expr(input)
+        parse_seq_expr(input, ParseContext::default())
     }
 }
 
-fn parse_seq_expr(input: ParseStream) -> syn::Result<PatternExpr> {
+fn parse_seq_expr(input: ParseStream, mut ctx: ParseContext) -> syn::Result<PatternExpr> {
     let mut terms = Vec::new();
     loop {
-        terms.push(parse_or_expr(input)?);
+        while let Some(case_insensitive) = parse_mode_flag(input)? {
+            ctx.case_insensitive = case_insensitive;
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        terms.push(parse_or_expr(input, ctx)?);
         if input.peek(Token![,]) {
             input.parse::<Token![,]>()?;
         } else {
@@ -63,10 +167,39 @@ fn parse_seq_expr(input: ParseStream) -> syn::Result<PatternExpr> {
     }
 }
 
-fn parse_or_expr(input: ParseStream) -> syn::Result<PatternExpr> {
+/// Parse a `(?i)` or `(?-i)` flag, returning whether case-insensitive mode is now on.
+/// Parentheses that do not start with `?` are a group and are left for `parse_term`.
+fn parse_mode_flag(input: ParseStream) -> syn::Result<Option<bool>> {
+    if !input.peek(syn::token::Paren) {
+        return Ok(None);
+    }
+    let fork = input.fork();
+    let content;
+    syn::parenthesized!(content in fork);
+    if !content.peek(Token![?]) {
+        return Ok(None);
+    }
+    let content;
+    syn::parenthesized!(content in input);
+    content.parse::<Token![?]>()?;
+    let negated = content.peek(Token![-]);
+    if negated {
+        content.parse::<Token![-]>()?;
+    }
+    let flag: Ident = content.parse()?;
+    if flag != "i" || !content.is_empty() {
+        return Err(syn::Error::new(
+            flag.span(),
+            "unsupported flag, expected `(?i)` or `(?-i)`",
+        ));
+    }
+    Ok(Some(!negated))
+}
+
+fn parse_or_expr(input: ParseStream, ctx: ParseContext) -> syn::Result<PatternExpr> {
     let mut terms = Vec::new();
     loop {
-        terms.push(parse_and_expr(input)?);
+        terms.push(parse_and_expr(input, ctx)?);
         if input.peek(Token![/]) {
             input.parse::<Token![/]>()?;
         } else {
@@ -82,13 +215,13 @@ fn parse_or_expr(input: ParseStream) -> syn::Result<PatternExpr> {
     }
 }
 
-fn parse_and_expr(input: ParseStream) -> syn::Result<PatternExpr> {
-    let expr = parse_term(input)?;
+fn parse_and_expr(input: ParseStream, ctx: ParseContext) -> syn::Result<PatternExpr> {
+    let expr = parse_term(input, ctx)?;
     // support bracketed separator syntax: `elem[sep]+` => Sep(elem, sep)
     if input.peek(syn::token::Bracket) {
         let content;
         syn::bracketed!(content in input);
-        let sep = parse_or_expr(&content)?;
+        let sep = parse_or_expr(&content, ctx)?;
         if input.peek(Token![+]) {
             input.parse::<Token![+]>()?;
             return Ok(PatternExpr {
@@ -107,6 +240,15 @@ fn parse_and_expr(input: ParseStream) -> syn::Result<PatternExpr> {
         }
     }
 
+    if input.peek(syn::token::Brace) {
+        let content;
+        syn::braced!(content in input);
+        let (min, max) = parse_repeat_bounds(&content)?;
+        return Ok(PatternExpr {
+            kind: PatternKind::Repeat(Box::new(expr), min, max),
+        });
+    }
+
     if input.peek(Token![+]) {
         input.parse::<Token![+]>()?;
         Ok(PatternExpr {
@@ -117,34 +259,178 @@ fn parse_and_expr(input: ParseStream) -> syn::Result<PatternExpr> {
         Ok(PatternExpr {
             kind: PatternKind::Some(Box::new(expr)),
         })
+    } else if input.peek(Token![?]) {
+        input.parse::<Token![?]>()?;
+        Ok(PatternExpr {
+            kind: PatternKind::Opt(Box::new(expr)),
+        })
     } else {
         Ok(expr)
     }
 }
 
-fn parse_term(input: ParseStream) -> syn::Result<PatternExpr> {
+/// Parse `sep!(elem, sep)`, `sep1!(elem, sep)` or `sep_trailing!(elem, sep)`, spelled-out
+/// forms of the `elem[sep]+` and `elem[sep]*` postfix syntax, or `fmt_pat!(...)`, whose
+/// arguments are passed on to `format_args!` untouched.
+fn parse_pattern_macro(input: ParseStream, ctx: ParseContext) -> syn::Result<PatternExpr> {
+    let name: Ident = input.parse()?;
+    input.parse::<Token![!]>()?;
+    let content;
+    syn::parenthesized!(content in input);
+    if name == "fmt_pat" {
+        let kind = PatternKind::Format(content.parse()?);
+        return Ok(PatternExpr { kind });
+    }
+    let elem = Box::new(parse_or_expr(&content, ctx)?);
+    content.parse::<Token![,]>()?;
+    let sep = Box::new(parse_or_expr(&content, ctx)?);
+    if content.peek(Token![,]) {
+        content.parse::<Token![,]>()?;
+    }
+    let kind = if name == "sep" {
+        PatternKind::Sep(elem, sep)
+    } else if name == "sep1" {
+        PatternKind::Sep1(elem, sep)
+    } else if name == "sep_trailing" {
+        PatternKind::SepTerminated(elem, sep)
+    } else {
+        return Err(syn::Error::new(
+            name.span(),
+            "unknown pattern macro, expected `sep!`, `sep1!`, `sep_trailing!` or `fmt_pat!`",
+        ));
+    };
+    Ok(PatternExpr { kind })
+}
+
+/// Parse the `n`, `min,max`, `min..end` or `min..=max` bounds of a `p{...}` repetition
+/// into inclusive bounds.
+fn parse_repeat_bounds(input: ParseStream) -> syn::Result<(usize, usize)> {
+    let min: syn::LitInt = input.parse()?;
+    if input.is_empty() {
+        let count = min.base10_parse::<usize>()?;
+        return Ok((count, count));
+    }
+    // `min,max` is inclusive, as `min..=max` is
+    let inclusive = input.peek(Token![..=]) || input.peek(Token![,]);
+    if input.peek(Token![,]) {
+        input.parse::<Token![,]>()?;
+    } else if inclusive {
+        input.parse::<Token![..=]>()?;
+    } else {
+        input.parse::<Token![..]>()?;
+    }
+    let end: syn::LitInt = input.parse()?;
+    let (min_value, end_value) = (min.base10_parse::<usize>()?, end.base10_parse::<usize>()?);
+    let max = if inclusive {
+        Some(end_value)
+    } else {
+        end_value.checked_sub(1)
+    };
+    match max {
+        Some(max) if max >= min_value => Ok((min_value, max)),
+        _ => Err(syn::Error::new(
+            end.span(),
+            "repetition range is empty; the end must not be below the start",
+        )),
+    }
+}
+
+fn parse_term(input: ParseStream, ctx: ParseContext) -> syn::Result<PatternExpr> {
+    // A bracket at the start of a term is a character class (`[a, b]` or `[^a, b]`);
+    // a bracket right after a term is the separator syntax handled in `parse_and_expr`.
+    if input.peek(syn::token::Bracket) {
+        let content;
+        syn::bracketed!(content in input);
+        let negated = content.peek(Token![^]);
+        if negated {
+            content.parse::<Token![^]>()?;
+        }
+        let lits = content.parse_terminated(syn::Lit::parse, Token![,])?;
+        let lits = lits.into_iter().collect();
+        return Ok(PatternExpr {
+            kind: if negated {
+                PatternKind::NoneOf(lits)
+            } else {
+                PatternKind::AnyOf(lits)
+            },
+        });
+    }
+
     if input.peek(syn::token::Paren) {
         let content;
         syn::parenthesized!(content in input);
         // parentheses act as grouping/sequence; parse inner sequence expression
-        let inner = parse_seq_expr(&content)?;
+        let inner = parse_seq_expr(&content, ctx)?;
         return Ok(inner);
     }
 
+    if input.peek(Ident) && input.peek2(Token![!]) {
+        return parse_pattern_macro(input, ctx);
+    }
+
+    if input.peek(Token![&]) {
+        input.parse::<Token![&]>()?;
+        let inner = parse_term(input, ctx)?;
+        return Ok(PatternExpr {
+            kind: PatternKind::Lookahead(Box::new(inner)),
+        });
+    }
+
+    if input.peek(Token![!]) {
+        input.parse::<Token![!]>()?;
+        let inner = parse_term(input, ctx)?;
+        return Ok(PatternExpr {
+            kind: PatternKind::NegLookahead(Box::new(inner)),
+        });
+    }
+
     if input.peek(Ident) && input.peek2(Token![@]) {
         let ident: Ident = input.parse()?;
         input.parse::<Token![@]>()?;
-        let expr = parse_or_expr(input)?;
+        let expr = parse_or_expr(input, ctx)?;
         return Ok(PatternExpr {
             kind: PatternKind::To(ident, Box::new(expr)),
         });
     }
 
+    if input.peek(Token![..]) {
+        input.parse::<Token![..]>()?;
+        return Ok(PatternExpr {
+            kind: PatternKind::Rest,
+        });
+    }
+
+    if input.peek(Token![$]) {
+        input.parse::<Token![$]>()?;
+        return Ok(PatternExpr {
+            kind: PatternKind::End,
+        });
+    }
+
     if input.peek(syn::Lit) {
         let lit: syn::Lit = input.parse()?;
-        return Ok(PatternExpr {
+        if input.peek(Token![..=]) {
+            input.parse::<Token![..=]>()?;
+            return Ok(PatternExpr {
+                kind: PatternKind::Range(lit, input.parse()?, true),
+            });
+        }
+        let fork = input.fork();
+        if fork.parse::<Token![..]>().is_ok() && fork.peek(syn::Lit) {
+            input.parse::<Token![..]>()?;
+            return Ok(PatternExpr {
+                kind: PatternKind::Range(lit, input.parse()?, false),
+            });
+        }
+        let expr = PatternExpr {
             kind: PatternKind::Lit(lit),
-        });
+        };
+        if ctx.case_insensitive && std::matches!(expr.kind, PatternKind::Lit(syn::Lit::Str(_))) {
+            return Ok(PatternExpr {
+                kind: PatternKind::CaseInsensitive(Box::new(expr)),
+            });
+        }
+        return Ok(expr);
     }
 
     if input.peek(Ident) {
@@ -156,7 +442,7 @@ fn parse_term(input: ParseStream) -> syn::Result<PatternExpr> {
 
     Err(syn::Error::new(
         input.span(),
-        "expected literal, identifier, grouped expression, or to",
+        "expected literal, identifier, character class, `..`, `$`, grouped expression, or to",
     ))
 }
 
@@ -204,6 +490,18 @@ fn build_pattern_tokens(pattern: &PatternExpr) -> proc_macro2::TokenStream {
             let inner = build_pattern_tokens(expr);
             quote! { RangeTo { end: #inner } }
         }
+        PatternKind::Opt(expr) => {
+            let inner = build_pattern_tokens(expr);
+            quote! { Opt(#inner) }
+        }
+        PatternKind::Lookahead(expr) => {
+            let inner = build_pattern_tokens(expr);
+            quote! { Lookahead(#inner) }
+        }
+        PatternKind::NegLookahead(expr) => {
+            let inner = build_pattern_tokens(expr);
+            quote! { NegLookahead(#inner) }
+        }
         PatternKind::Sep(elem, sep) => {
             let e = build_pattern_tokens(elem);
             let s = build_pattern_tokens(sep);
@@ -214,10 +512,158 @@ fn build_pattern_tokens(pattern: &PatternExpr) -> proc_macro2::TokenStream {
             let s = build_pattern_tokens(sep);
             quote! { Sep1(#s, #e) }
         }
+        PatternKind::SepTerminated(elem, sep) => {
+            let e = build_pattern_tokens(elem);
+            let s = build_pattern_tokens(sep);
+            quote! { SepTerminated(#s, #e) }
+        }
         PatternKind::To(ident, expr) => {
             let inner = build_pattern_tokens(expr);
             quote! { To(#inner, &#ident) }
         }
+        PatternKind::AnyOf(lits) => quote! { AnyOf([#(#lits),*]) },
+        PatternKind::NoneOf(lits) => quote! { NoneOf([#(#lits),*]) },
+        PatternKind::Rest => quote! { ::core::ops::RangeFull },
+        PatternKind::End => quote! { End },
+        PatternKind::Range(start, end, true) => {
+            quote! { ::core::ops::RangeInclusive::new(#start, #end) }
+        }
+        PatternKind::Range(start, end, false) => {
+            quote! { ::core::ops::Range { start: #start, end: #end } }
+        }
+        PatternKind::CaseInsensitive(expr) => {
+            let inner = build_pattern_tokens(expr);
+            quote! { CaseInsensitive(#inner) }
+        }
+        PatternKind::Repeat(expr, min, max) if min == max => {
+            let inner = build_pattern_tokens(expr);
+            quote! { RepeatExact(#inner, #min) }
+        }
+        PatternKind::Repeat(expr, min, max) => {
+            let inner = build_pattern_tokens(expr);
+            quote! { RepeatBetween(#inner, #min..=#max) }
+        }
+        PatternKind::Format(args) => quote! { FormatPattern(format_args!(#args)) },
+    }
+}
+
+/// Build the destructuring pattern for the pattern's `Dest`, binding each `name @ term`
+/// capture to its own value and ignoring everything else. The nesting mirrors
+/// `build_pattern_tokens`.
+fn build_bind_tokens(
+    pattern: &PatternExpr,
+    names: &mut Vec<Ident>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    match &pattern.kind {
+        PatternKind::To(ident, expr) => {
+            if has_capture(expr) {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "nested captures are not supported by `matches_bind!`",
+                ));
+            }
+            names.push(ident.clone());
+            Ok(quote! { #ident })
+        }
+        PatternKind::Tuple(exprs) if exprs.len() > 1 => {
+            let mut tokens = build_bind_tokens(&exprs[0], names)?;
+            for expr in &exprs[1..] {
+                let inner = build_bind_tokens(expr, names)?;
+                tokens = quote! { (#tokens, #inner) };
+            }
+            Ok(tokens)
+        }
+        _ if has_capture(pattern) => Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "`matches_bind!` captures cannot appear inside alternatives or repetitions; capture the whole group instead",
+        )),
+        _ => Ok(quote! { _ }),
+    }
+}
+
+fn has_capture(pattern: &PatternExpr) -> bool {
+    match &pattern.kind {
+        PatternKind::To(..) => true,
+        PatternKind::Tuple(exprs) | PatternKind::Or(exprs) => exprs.iter().any(has_capture),
+        PatternKind::Many(expr)
+        | PatternKind::Some(expr)
+        | PatternKind::Opt(expr)
+        | PatternKind::Lookahead(expr)
+        | PatternKind::NegLookahead(expr)
+        | PatternKind::CaseInsensitive(expr)
+        | PatternKind::Repeat(expr, ..) => has_capture(expr),
+        PatternKind::Sep(elem, sep)
+        | PatternKind::Sep1(elem, sep)
+        | PatternKind::SepTerminated(elem, sep) => has_capture(elem) || has_capture(sep),
+        PatternKind::Lit(_)
+        | PatternKind::Ident(_)
+        | PatternKind::AnyOf(_)
+        | PatternKind::NoneOf(_)
+        | PatternKind::Rest
+        | PatternKind::End
+        | PatternKind::Range(..)
+        | PatternKind::Format(_) => false,
+    }
+}
+
+/// Collect the identifier terms of a pattern. Each is checked on its own so that a
+/// non-pattern identifier is reported at its span instead of deep inside the expansion.
+fn collect_idents<'p>(pattern: &'p PatternExpr, idents: &mut Vec<&'p Ident>) {
+    match &pattern.kind {
+        PatternKind::Ident(ident) => idents.push(ident),
+        PatternKind::Tuple(exprs) | PatternKind::Or(exprs) => {
+            exprs.iter().for_each(|e| collect_idents(e, idents))
+        }
+        PatternKind::Many(expr)
+        | PatternKind::Some(expr)
+        | PatternKind::Opt(expr)
+        | PatternKind::Lookahead(expr)
+        | PatternKind::NegLookahead(expr)
+        | PatternKind::To(_, expr)
+        | PatternKind::CaseInsensitive(expr)
+        | PatternKind::Repeat(expr, ..) => collect_idents(expr, idents),
+        PatternKind::Sep(elem, sep)
+        | PatternKind::Sep1(elem, sep)
+        | PatternKind::SepTerminated(elem, sep) => {
+            collect_idents(elem, idents);
+            collect_idents(sep, idents);
+        }
+        PatternKind::Lit(_)
+        | PatternKind::AnyOf(_)
+        | PatternKind::NoneOf(_)
+        | PatternKind::Rest
+        | PatternKind::End
+        | PatternKind::Range(..)
+        | PatternKind::Format(_) => {}
+    }
+}
+
+/// Emit one `__assert_pattern` call per identifier term, spanned at the identifier.
+fn build_assert_tokens(pattern: &PatternExpr) -> proc_macro2::TokenStream {
+    let mut idents = Vec::new();
+    collect_idents(pattern, &mut idents);
+    let asserts = idents.into_iter().map(|ident| {
+        quote_spanned! {ident.span()=>
+            crate::__assert_pattern(&#ident, __reference);
+        }
+    });
+    quote! { #(#asserts)* }
+}
+
+/// Build the pattern as `matches!` would, except that `name @ term` captures emit only
+/// the term: their values are read back out of the pattern's `Dest` instead.
+fn build_bind_pattern_tokens(pattern: &PatternExpr) -> proc_macro2::TokenStream {
+    match &pattern.kind {
+        PatternKind::To(_, expr) => build_bind_pattern_tokens(expr),
+        PatternKind::Tuple(exprs) if exprs.len() > 1 => {
+            let mut tokens = build_bind_pattern_tokens(&exprs[0]);
+            for expr in &exprs[1..] {
+                let inner = build_bind_pattern_tokens(expr);
+                tokens = quote! { (#tokens, #inner) };
+            }
+            tokens
+        }
+        _ => build_pattern_tokens(pattern),
     }
 }
 
@@ -229,10 +675,450 @@ pub fn matches(item: TokenStream) -> TokenStream {
 
     let reference = input.reference;
 
+    let asserts = build_assert_tokens(&input.pattern);
+
+    let on_fail = input
+        .else_branch
+        .map(|block| quote! { if !__result #block });
+
     let output = quote!({
+        let __reference = & #reference;
+        #asserts
         let __pattern = #pattern_tokens;
-        crate::__matches(&__pattern, & #reference)
+        let __result = crate::__matches(&__pattern, __reference);
+        #on_fail
+        __result
     });
 
     output.into()
 }
+
+/// Build a `match` over the pattern's captured `Dest`, evaluating to `on_match` with the
+/// `name @ term` captures bound, or to `on_fail`. Shared by `matches_bind!` and `match_or!`.
+fn build_bind_match(
+    pattern: &PatternExpr,
+    reference: &Expr,
+    on_match: impl FnOnce(proc_macro2::TokenStream) -> proc_macro2::TokenStream,
+    on_fail: proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut names = Vec::new();
+    let bind_tokens = build_bind_tokens(pattern, &mut names)?;
+    let pattern_tokens = build_bind_pattern_tokens(pattern);
+    let values = if names.len() == 1 {
+        quote! { #(#names)* }
+    } else {
+        quote! { (#(#names),*) }
+    };
+    let on_match = on_match(values);
+
+    let asserts = build_assert_tokens(pattern);
+
+    Ok(quote!({
+        let __reference = & #reference;
+        #asserts
+        let __pattern = #pattern_tokens;
+        match crate::__matches_dest(&__pattern, __reference) {
+            #[allow(unused_variables)]
+            Some(#bind_tokens) => #on_match,
+            None => #on_fail,
+        }
+    }))
+}
+
+#[proc_macro]
+pub fn matches_bind(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as MatchesInput);
+
+    let on_fail = match input.else_branch {
+        Some(block) => quote! {{ #block; None }},
+        None => quote! { None },
+    };
+
+    build_bind_match(
+        &input.pattern,
+        &input.reference,
+        |values| quote! { Some(#values) },
+        on_fail,
+    )
+    .unwrap_or_else(|err| err.to_compile_error())
+    .into()
+}
+
+/// Like `matches_bind!`, but evaluates to the captures directly on success and to the
+/// fallback expression otherwise. The fallback is only evaluated when matching fails, so
+/// it may `return` or use `?`.
+#[proc_macro]
+pub fn match_or(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as MatchOrInput);
+
+    let fallback = input.fallback;
+
+    build_bind_match(
+        &input.pattern,
+        &input.reference,
+        |values| values,
+        quote! { #fallback },
+    )
+    .unwrap_or_else(|err| err.to_compile_error())
+    .into()
+}
+
+#[proc_macro]
+pub fn trim(item: TokenStream) -> TokenStream {
+    let pattern = parse_macro_input!(item as Expr);
+
+    let output = quote!({
+        (crate::exts::WS_OPT, #pattern, crate::exts::WS_OPT)
+    });
+
+    output.into()
+}
+
+fn parse_pattern_list(item: TokenStream) -> syn::Result<Vec<Expr>> {
+    let parser = syn::punctuated::Punctuated::<Expr, Token![,]>::parse_terminated;
+    let exprs: Vec<Expr> = syn::parse::Parser::parse(parser, item)?
+        .into_iter()
+        .collect();
+    if exprs.is_empty() {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "expected at least one pattern",
+        ));
+    }
+    Ok(exprs)
+}
+
+#[proc_macro]
+pub fn seq(item: TokenStream) -> TokenStream {
+    let exprs = match parse_pattern_list(item) {
+        Ok(exprs) => exprs,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    // Right-associated pairs: seq!(a, b, c) => (a, (b, c))
+    let mut rev = exprs.into_iter().rev();
+    let mut tokens = rev.next().map(|last| quote! { #last }).unwrap();
+    for expr in rev {
+        tokens = quote! { (#expr, #tokens) };
+    }
+
+    quote!({ #tokens }).into()
+}
+
+#[proc_macro]
+pub fn alt(item: TokenStream) -> TokenStream {
+    let exprs = match parse_pattern_list(item) {
+        Ok(exprs) => exprs,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    // Right-associated alternatives: alt!(a, b, c) => Or(a, Or(b, c))
+    let mut rev = exprs.into_iter().rev();
+    let mut tokens = rev.next().map(|last| quote! { #last }).unwrap();
+    for expr in rev {
+        tokens = quote! { Or(#expr, #tokens) };
+    }
+
+    quote!({ #tokens }).into()
+}
+
+/// Chain two or more alternatives into right-associated `Or`s. Every alternative must
+/// capture into the same `Dest` type. `Dest` depends on the input type, which is not
+/// known here, so a mismatch is reported where the pattern is first matched against
+/// input. Use `or_any!` when the captures differ.
+#[proc_macro]
+pub fn or(item: TokenStream) -> TokenStream {
+    build_or_chain(item, |expr| quote! { #expr })
+}
+
+/// Like `or!`, but each alternative's capture is discarded, so alternatives with
+/// different `Dest` types can be mixed. The resulting pattern's `Dest` is `()`.
+#[proc_macro]
+pub fn or_any(item: TokenStream) -> TokenStream {
+    build_or_chain(item, |expr| quote! { Discard(#expr) })
+}
+
+fn build_or_chain(
+    item: TokenStream,
+    wrap: impl Fn(&Expr) -> proc_macro2::TokenStream,
+) -> TokenStream {
+    let exprs = match parse_pattern_list(item) {
+        Ok(exprs) => exprs,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    if exprs.len() < 2 {
+        return syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "expected at least two alternatives",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let mut rev = exprs.iter().rev();
+    let mut tokens = rev.next().map(&wrap).unwrap();
+    for expr in rev {
+        let head = wrap(expr);
+        tokens = quote! { Or(#head, #tokens) };
+    }
+
+    quote!({ #tokens }).into()
+}
+
+#[proc_macro]
+pub fn compile_pattern(item: TokenStream) -> TokenStream {
+    let CompilePatternInput {
+        vis,
+        name,
+        ty,
+        pattern,
+    } = parse_macro_input!(item as CompilePatternInput);
+
+    // A `static` initializer must be a constant expression, so patterns built from
+    // runtime values are rejected by the compiler at this point.
+    let output = quote! {
+        #vis static #name: #ty = #pattern;
+    };
+
+    output.into()
+}
+
+/// Derive `FromPattern` for a tuple struct, reading its fields in declaration order.
+/// Each field names its sub-pattern with `#[pattern(...)]`, and its capture is converted
+/// to the field type with `TryFrom`. A struct-level `#[pattern(sep = ...)]` is matched
+/// between fields. The struct also gets a `PATTERN` constant to match and capture it.
+#[proc_macro_derive(Pattern, attributes(pattern))]
+pub fn derive_pattern(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as syn::DeriveInput);
+    derive_pattern_tokens(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn derive_pattern_tokens(input: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`#[derive(Pattern)]` does not support generic structs",
+        ));
+    }
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Unnamed(fields),
+            ..
+        }) => &fields.unnamed,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "`#[derive(Pattern)]` only supports tuple structs",
+            ));
+        }
+    };
+
+    let mut sep: Option<Expr> = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("pattern")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("sep") {
+                sep = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `sep = <pattern>`"))
+            }
+        })?;
+    }
+
+    let mut steps = Vec::new();
+    let mut values = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let pattern = field
+            .attrs
+            .iter()
+            .find(|a| a.path().is_ident("pattern"))
+            .ok_or_else(|| syn::Error::new_spanned(field, "missing `#[pattern(...)]` on field"))?
+            .parse_args::<Expr>()?;
+        if i > 0
+            && let Some(sep) = &sep
+        {
+            steps.push(quote! { crate::__consume_field(&#sep, reference)?; });
+        }
+        let var = quote::format_ident!("__field{}", i);
+        let ty = &field.ty;
+        steps.push(quote! { let #var = crate::__consume_field(&#pattern, reference)?; });
+        values.push(quote! { <#ty as ::core::convert::TryFrom<_>>::try_from(#var).ok()? });
+    }
+
+    Ok(quote! {
+        impl crate::base::Destination<char> for #name {}
+
+        impl crate::FromPattern for #name {
+            fn consume_from(
+                reference: &mut crate::Checkpoint<::std::iter::Peekable<::std::str::Chars<'_>>>,
+            ) -> ::core::option::Option<Self> {
+                #(#steps)*
+                ::core::option::Option::Some(#name(#(#values),*))
+            }
+        }
+
+        impl #name {
+            pub const PATTERN: crate::Parsed<#name> = crate::Parsed::new();
+        }
+    })
+}
+
+/// Derive `captures_from(&str) -> Option<Self>` for a struct with named fields. Each
+/// field names its pattern with `#[capture(pattern = ...)]` and may add
+/// `prefix = ...` for a pattern matched and discarded just before it. The fields are
+/// matched in declaration order and must cover the whole input; each capture is
+/// converted into its field's type with `TryFrom`.
+#[proc_macro_derive(Captures, attributes(capture))]
+pub fn derive_captures(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as syn::DeriveInput);
+    derive_captures_tokens(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn derive_captures_tokens(input: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`#[derive(Captures)]` does not support generic structs",
+        ));
+    }
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "`#[derive(Captures)]` only supports structs with named fields",
+            ));
+        }
+    };
+
+    let mut steps = Vec::new();
+    let mut values = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let mut pattern: Option<Expr> = None;
+        let mut prefix: Option<Expr> = None;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("capture")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("pattern") {
+                    pattern = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("prefix") {
+                    prefix = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `pattern = <pattern>` or `prefix = <pattern>`"))
+                }
+            })?;
+        }
+        let pattern = pattern.ok_or_else(|| {
+            syn::Error::new_spanned(field, "missing `#[capture(pattern = ...)]` on field")
+        })?;
+        if let Some(prefix) = prefix {
+            steps.push(quote! { crate::__consume_field(&#prefix, &mut reference)?; });
+        }
+        let ty = &field.ty;
+        steps.push(quote! { let #ident = crate::__consume_field(&#pattern, &mut reference)?; });
+        values
+            .push(quote! { #ident: <#ty as ::core::convert::TryFrom<_>>::try_from(#ident).ok()? });
+    }
+
+    Ok(quote! {
+        impl #name {
+            /// Match the whole input against the fields' patterns in order, returning
+            /// the captured struct on success.
+            pub fn captures_from(input: &str) -> ::core::option::Option<Self> {
+                let mut reference = crate::Checkpoint::new(input.chars().peekable());
+                #(#steps)*
+                if crate::base::PeekableExt::peek(&mut reference).is_some() {
+                    return ::core::option::Option::None;
+                }
+                ::core::option::Option::Some(#name { #(#values),* })
+            }
+        }
+    })
+}
+
+/// Arguments of `#[bench_pattern(input = "...", iterations = N)]`.
+#[cfg(feature = "bench")]
+struct BenchPatternArgs {
+    input: syn::LitStr,
+    iterations: syn::LitInt,
+}
+
+#[cfg(feature = "bench")]
+impl Parse for BenchPatternArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut bench_input = None;
+        let mut iterations = None;
+        let parser = syn::meta::parser(|meta| {
+            if meta.path.is_ident("input") {
+                bench_input = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("iterations") {
+                iterations = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `input = \"...\"` or `iterations = <count>`"))
+            }
+        });
+        syn::parse::Parser::parse2(parser, input.parse()?)?;
+        Ok(BenchPatternArgs {
+            input: bench_input.ok_or_else(|| input.error("missing `input = \"...\"`"))?,
+            iterations: iterations
+                .unwrap_or_else(|| syn::LitInt::new("10000", proc_macro2::Span::call_site())),
+        })
+    }
+}
+
+/// Benchmark a matching function taking the input as its only argument, such as
+/// `fn ip(input: &str) -> bool { matches!(input => NUM, ".", NUM) }`. The function is
+/// kept as is, and a `bench_<name>` test is added that calls it `iterations` times
+/// (10000 by default) on `input` and prints the average time per match. Run it with
+/// `cargo test bench_ -- --nocapture`.
+#[cfg(feature = "bench")]
+#[proc_macro_attribute]
+pub fn bench_pattern(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let BenchPatternArgs { input, iterations } = parse_macro_input!(attr as BenchPatternArgs);
+    let func = parse_macro_input!(item as syn::ItemFn);
+    if func.sig.inputs.len() != 1 {
+        return syn::Error::new_spanned(
+            &func.sig,
+            "`#[bench_pattern]` expects a function taking the input as its only argument",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let name = &func.sig.ident;
+    let bench_name = quote::format_ident!("bench_{}", name);
+    let label = format!("{}({:?})", name, input.value());
+    quote! {
+        #func
+
+        #[test]
+        fn #bench_name() {
+            let iterations: u32 = #iterations;
+            let start = ::std::time::Instant::now();
+            for _ in 0..iterations {
+                ::std::hint::black_box(#name(::std::hint::black_box(#input)));
+            }
+            let elapsed = start.elapsed();
+            ::std::println!(
+                "{}: {} ns/match over {} iterations",
+                #label,
+                elapsed.as_nanos() / u128::from(iterations.max(1)),
+                iterations,
+            );
+        }
+    }
+    .into()
+}
@@ -1,13 +1,29 @@
 use core::slice;
 use std::{
+    borrow::Cow,
     cell::RefCell,
-    ops::{RangeTo, RangeToInclusive},
+    ops::{Range, RangeFull, RangeInclusive, RangeTo, RangeToInclusive},
     str::Chars,
 };
 
 use crate::dest;
+#[cfg(feature = "debug-report")]
+use crate::utils::DebugMatchReport;
+use crate::utils::{ErrorKind, HCons, HNil, MatchResult, PatternError, ResultHList};
 
-use std::collections::VecDeque;
+use std::collections::{BTreeSet, BinaryHeap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+/// Counters collected by a `Checkpoint` created with `Checkpoint::with_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CheckpointStats {
+    pub clones: usize,
+    pub rollbacks: usize,
+    pub items_consumed: usize,
+    pub items_buffered_peak: usize,
+}
 
 /// An iterator wrapper that supports checkpointing (snapshots and rollbacks).
 pub struct Checkpoint<I>
@@ -18,6 +34,13 @@ where
     front: VecDeque<I::Item>,
     trail: Vec<I::Item>,
     in_trial: bool,
+    previous: Option<I::Item>,
+    trial_previous: Option<I::Item>,
+    lookbehind: VecDeque<I::Item>,
+    trial_lookbehind: VecDeque<I::Item>,
+    lookbehind_capacity: usize,
+    position: usize,
+    stats: Option<Arc<Mutex<CheckpointStats>>>,
 }
 
 impl<I> Checkpoint<I>
@@ -31,12 +54,93 @@ where
             front: VecDeque::new(),
             trail: Vec::new(),
             in_trial: false,
+            previous: None,
+            trial_previous: None,
+            lookbehind: VecDeque::new(),
+            trial_lookbehind: VecDeque::new(),
+            lookbehind_capacity: 0,
+            position: 0,
+            stats: None,
+        }
+    }
+
+    /// Create a checkpoint that remembers the last `capacity` consumed items, for
+    /// `lookbehind`.
+    pub fn new_with_lookbehind(inner: I, capacity: usize) -> Self {
+        Checkpoint {
+            lookbehind: VecDeque::with_capacity(capacity),
+            lookbehind_capacity: capacity,
+            ..Checkpoint::new(inner)
+        }
+    }
+
+    /// The `n`th most recently consumed item, where `lookbehind(0)` is the last one.
+    /// Returns `None` past the capacity given to `new_with_lookbehind`.
+    pub fn lookbehind(&self, n: usize) -> Option<&I::Item> {
+        let len = self.lookbehind.len();
+        n.checked_add(1)
+            .and_then(|back| len.checked_sub(back))
+            .and_then(|i| self.lookbehind.get(i))
+    }
+
+    /// Create a checkpoint that counts its clones, rollbacks and consumed items. Clones
+    /// share the returned counters.
+    pub fn with_stats(inner: I) -> (Self, Arc<Mutex<CheckpointStats>>) {
+        let stats = Arc::new(Mutex::new(CheckpointStats::default()));
+        let checkpoint = Checkpoint {
+            stats: Some(stats.clone()),
+            ..Checkpoint::new(inner)
+        };
+        (checkpoint, stats)
+    }
+
+    /// Peek `n` items ahead, where `peek_ahead(0)` is the next item. Items up to the `n`th
+    /// are pulled from the inner iterator into the buffer once, so repeated lookahead
+    /// does not need to clone the checkpoint.
+    pub fn peek_ahead(&mut self, n: usize) -> Option<&I::Item> {
+        while self.front.len() <= n {
+            let item = self.inner.next()?;
+            self.front.push_back(item);
+        }
+        let buffered = self.front.len();
+        self.record(|stats| {
+            stats.items_buffered_peak = stats.items_buffered_peak.max(buffered);
+        });
+        self.front.get(n)
+    }
+
+    /// Pull items into the lookahead buffer until one is accepted by `stop` or the input
+    /// ends, returning everything buffered. Backs `BufRead::fill_buf`.
+    #[cfg(feature = "io")]
+    pub(crate) fn fill_front_until(&mut self, stop: impl Fn(&I::Item) -> bool) -> &[I::Item] {
+        if !self.front.iter().any(&stop) {
+            for item in self.inner.by_ref() {
+                let done = stop(&item);
+                self.front.push_back(item);
+                if done {
+                    break;
+                }
+            }
         }
+        self.front.make_contiguous()
+    }
+
+    /// The item most recently returned by `next`, if any.
+    pub fn previous(&self) -> Option<&I::Item> {
+        self.previous.as_ref()
+    }
+
+    /// How many items have been consumed since `new`, not counting those a `rollback`
+    /// has put back.
+    pub fn position(&self) -> usize {
+        self.position
     }
 
     pub fn begin(&mut self) {
         self.trail.clear();
         self.in_trial = true;
+        self.trial_previous = self.previous.clone();
+        self.trial_lookbehind.clone_from(&self.lookbehind);
     }
 
     pub fn commit(&mut self) {
@@ -45,11 +149,71 @@ where
     }
 
     pub fn rollback(&mut self) {
+        self.position -= self.trail.len();
         // Move trail items to the front in original order
         while let Some(it) = self.trail.pop() {
             self.front.push_front(it);
         }
+        self.previous = self.trial_previous.take();
+        std::mem::swap(&mut self.lookbehind, &mut self.trial_lookbehind);
         self.in_trial = false;
+        let buffered = self.front.len();
+        self.record(|stats| {
+            stats.rollbacks += 1;
+            stats.items_buffered_peak = stats.items_buffered_peak.max(buffered);
+        });
+    }
+}
+
+impl<I> Checkpoint<I>
+where
+    I: Iterator + Clone + PeekableExt,
+    I::Item: Clone,
+{
+    /// Find the first match of `pattern`, returning the items before it along with its
+    /// capture and advancing past the match, like `str::split_once`. Returns `None`,
+    /// leaving the checkpoint where it was, if the pattern never matches.
+    pub fn split_at_pattern<'a, P>(&mut self, pattern: &'a P) -> Option<(Vec<I::Item>, P::Dest)>
+    where
+        P: Pattern<'a, Self>,
+        P::Dest: Destination<I::Item> + Default,
+        I::Item: Satisfies<<P::Iter as Iterator>::Item>,
+    {
+        let mut cursor = self.clone();
+        let mut prefix = Vec::new();
+        loop {
+            let mut trial = cursor.clone();
+            let dest = RefCell::new(P::Dest::default());
+            if pattern.consume_with_dest(&mut trial, Some(&dest)) {
+                *self = trial;
+                return Some((prefix, dest.into_inner()));
+            }
+            prefix.push(cursor.next()?);
+        }
+    }
+}
+
+impl<I> Checkpoint<I>
+where
+    I: Iterator,
+{
+    fn remember(&mut self, item: &I::Item)
+    where
+        I::Item: Clone,
+    {
+        if self.lookbehind_capacity == 0 {
+            return;
+        }
+        if self.lookbehind.len() == self.lookbehind_capacity {
+            self.lookbehind.pop_front();
+        }
+        self.lookbehind.push_back(item.clone());
+    }
+
+    fn record(&self, update: impl FnOnce(&mut CheckpointStats)) {
+        if let Some(stats) = &self.stats {
+            update(&mut stats.lock().unwrap());
+        }
     }
 }
 
@@ -62,10 +226,21 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(it) = self.front.pop_front() {
+            self.previous = Some(it.clone());
+            self.remember(&it);
+            self.position += 1;
+            self.record(|stats| stats.items_consumed += 1);
+            if self.in_trial {
+                self.trail.push(it.clone());
+            }
             return Some(it);
         }
         match self.inner.next() {
             Some(it) => {
+                self.previous = Some(it.clone());
+                self.remember(&it);
+                self.position += 1;
+                self.record(|stats| stats.items_consumed += 1);
                 if self.in_trial {
                     // store a clone for potential rollback and return the original
                     self.trail.push(it.clone());
@@ -98,15 +273,32 @@ where
     I::Item: Clone,
 {
     fn clone(&self) -> Self {
+        self.record(|stats| stats.clones += 1);
         Checkpoint {
             inner: self.inner.clone(),
             front: self.front.clone(),
             trail: self.trail.clone(),
             in_trial: self.in_trial,
+            previous: self.previous.clone(),
+            trial_previous: self.trial_previous.clone(),
+            lookbehind: self.lookbehind.clone(),
+            trial_lookbehind: self.trial_lookbehind.clone(),
+            lookbehind_capacity: self.lookbehind_capacity,
+            position: self.position,
+            stats: self.stats.clone(),
         }
     }
 }
 
+/// Count how many items `trial` has advanced past `orig` by comparing their remainders.
+/// This walks both remainders, so it is O(n) in the remaining input.
+fn consumed_between<I>(orig: &I, trial: &I) -> usize
+where
+    I: Iterator + Clone,
+{
+    orig.clone().count().saturating_sub(trial.clone().count())
+}
+
 /// A trait for types that can be checked for satisfaction against another type.
 pub trait Satisfies<T> {
     fn satisfies(&self, item: &T) -> bool;
@@ -143,6 +335,34 @@ impl Destination<char> for String {
 
 impl<T> Destination<char> for Vec<T> where T: Destination<char> {}
 
+impl<T> Destination<&T> for BinaryHeap<T>
+where
+    T: Ord + Clone,
+{
+    fn pickup(&mut self, item: &T) {
+        self.push(item.clone());
+    }
+}
+
+impl<T> Destination<char> for BinaryHeap<T> where T: Destination<char> + Ord {}
+
+impl<Item> Destination<Item> for () {}
+
+impl Destination<char> for char {}
+
+/// Lets patterns over slices of plain values, such as sets, capture the item they read.
+macro_rules! impl_destination_by_ref {
+    ($($t:ty),+) => {
+        $(impl Destination<&$t> for $t {})+
+    };
+}
+
+impl_destination_by_ref!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, bool, char, String
+);
+
+impl<'s> Destination<&&'s str> for &'s str {}
+
 /// A trait for iterable reference types.
 pub trait Iterable<'a> {
     type Iter: Iterator;
@@ -166,6 +386,11 @@ where
 }
 
 /// A trait for pattern types that can match against a reference iterator.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a pattern over `{Reference}`",
+    label = "not a pattern for this input",
+    note = "pattern terms must be literals, pattern constants such as `NUM`, or combinators of them"
+)]
 pub trait Pattern<'a, Reference>
 where
     Reference: Iterator,
@@ -174,10 +399,19 @@ where
     type Dest;
     /// Get an iterator over the pattern's items.
     fn get_iter(&'a self) -> Self::Iter;
+    /// Describe the pattern in a human-readable form, for debugging.
+    fn explain(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
     /// Get a mutable reference to the pattern's internal destination, if any.
     fn get_dest_mut(&self) -> Option<std::cell::RefMut<'_, Self::Dest>> {
         None
     }
+    /// Whether the pattern can succeed without consuming anything. The default is
+    /// `false`; patterns that can match empty input, such as `..p` or `Sep`, say so.
+    fn matches_empty(&self) -> bool {
+        false
+    }
     /// Match the pattern against the reference iterator.
     fn matches<'s, R>(&'a self, reference: &'s R) -> bool
     where
@@ -189,6 +423,47 @@ where
         let mut iter = reference.get_iter();
         self.consume(&mut iter) && iter.peek().is_none()
     }
+    /// Match the pattern at the start of the reference, allowing trailing input.
+    /// Returns the number of items consumed; for `char` input this is a char count,
+    /// which `str::char_indices` turns into a byte offset.
+    fn matches_prefix<'s, R>(&'a self, reference: &'s R) -> Option<usize>
+    where
+        R: Iterable<'s, Iter = Reference> + 's,
+        Self::Dest: Destination<Reference::Item>,
+        Reference: PeekableExt + Clone,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let orig = reference.get_iter();
+        let mut iter = orig.clone();
+        if self.consume(&mut iter) {
+            Some(consumed_between(&orig, &iter))
+        } else {
+            None
+        }
+    }
+    /// The largest number of items the pattern can consume from the start of the input.
+    /// With the `ambiguous` feature every way of matching is considered; otherwise this
+    /// is the length of the pattern's own greedy match, as in `matches_prefix`.
+    fn longest_match<'s, R>(&'a self, input: &'s R) -> Option<usize>
+    where
+        R: Iterable<'s, Iter = Reference> + 's,
+        Self::Dest: Destination<Reference::Item> + Default + Clone,
+        Reference: PeekableExt + Clone,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        #[cfg(feature = "ambiguous")]
+        {
+            let orig = input.get_iter();
+            self.consume_all(&orig, Default::default())
+                .iter()
+                .map(|(rest, _)| consumed_between(&orig, rest))
+                .max()
+        }
+        #[cfg(not(feature = "ambiguous"))]
+        {
+            self.matches_prefix(input)
+        }
+    }
     /// Consume items from the reference iterator, optionally storing matched items in a destination.
     fn consume_with_dest(
         &'a self,
@@ -229,250 +504,2776 @@ where
     {
         self.consume_with_dest(reference_iter, None)
     }
-}
 
-impl<'a> Iterable<'a> for &'a str {
-    type Iter = Checkpoint<std::iter::Peekable<Chars<'a>>>;
-    fn get_iter(&'a self) -> Self::Iter {
-        Checkpoint::new(self.chars().peekable())
+    /// Check whether the pattern would match here, without consuming anything.
+    fn peek_match(&'a self, reference_iter: &mut Reference) -> bool
+    where
+        Reference: PeekableExt + Clone,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        self.consume(&mut reference_iter.clone())
     }
-}
 
-impl<'a, Reference> Pattern<'a, Reference> for &'a str
-where
-    Reference: Iterator<Item = char> + PeekableExt,
-{
-    type Iter = Checkpoint<std::iter::Peekable<Chars<'a>>>;
-    type Dest = String;
-    fn get_iter(&'a self) -> Self::Iter {
-        Checkpoint::new(self.chars().peekable())
+    /// Consume this pattern and then `continuation`, as the first element of a pair does.
+    /// Patterns that can match in more than one way, such as `Lazy`, override this to
+    /// retry with another match when the continuation fails.
+    fn consume_with_continuation<'c, C>(
+        &'a self,
+        reference_iter: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+        continuation: &'c C,
+        continuation_dest: Option<&RefCell<C::Dest>>,
+    ) -> bool
+    where
+        Reference: PeekableExt,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+        C: Pattern<'c, Reference>,
+        C::Dest: Destination<Reference::Item> + Clone,
+        Reference::Item: Satisfies<<C::Iter as Iterator>::Item>,
+    {
+        let head = match dest {
+            Some(_) => self.consume_with_dest(reference_iter, dest),
+            None => self.consume(reference_iter),
+        };
+        head && match continuation_dest {
+            Some(_) => continuation.consume_with_dest(reference_iter, continuation_dest),
+            None => continuation.consume(reference_iter),
+        }
     }
-}
 
-impl<'a> Iterable<'a> for String {
-    type Iter = Checkpoint<std::iter::Peekable<Chars<'a>>>;
-    fn get_iter(&'a self) -> Self::Iter {
-        Checkpoint::new(self.chars().peekable())
+    /// Consume items like `consume`, returning how many were consumed on success.
+    /// A generic reference has no position to read this from, so the remaining input is
+    /// counted before and after, which is O(n) in the length of the input. When matching
+    /// over a `Checkpoint`, comparing `Checkpoint::position` before and after is O(1).
+    fn count_consumed(&'a self, reference_iter: &mut Reference) -> Option<usize>
+    where
+        Reference: PeekableExt + Clone,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let orig = reference_iter.clone();
+        self.consume(reference_iter)
+            .then(|| consumed_between(&orig, reference_iter))
     }
-}
 
-impl<'a, Reference> Pattern<'a, Reference> for String
-where
-    Reference: Iterator<Item = char> + PeekableExt,
-{
-    type Iter = Checkpoint<std::iter::Peekable<Chars<'a>>>;
-    type Dest = String;
-    fn get_iter(&'a self) -> Self::Iter {
-        Checkpoint::new(self.chars().peekable())
+    /// Consume items like `consume`, describing where and why matching stopped if the
+    /// pattern fails. On failure nothing is consumed, and the error's position counts
+    /// items from where the attempt started.
+    fn try_consume_err(
+        &'a self,
+        reference_iter: &mut Reference,
+    ) -> Result<(), PatternError<Reference::Item>>
+    where
+        Reference: PeekableExt + Clone,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item> + Clone,
+    {
+        let mut trial = reference_iter.clone();
+        if self.consume(&mut trial) {
+            *reference_iter = trial;
+            return Ok(());
+        }
+        // A literal stops at the first mismatch, so the trial shows how far it got
+        let position = consumed_between(reference_iter, &trial);
+        Err(PatternError::at(&mut trial, position).within(self.explain()))
     }
-}
 
-impl<'a, T> Iterable<'a> for &'a [T]
-where
-    T: 'a,
-{
-    type Iter = std::iter::Peekable<slice::Iter<'a, T>>;
-    fn get_iter(&'a self) -> Self::Iter {
-        self.iter().peekable()
+    /// Consume items like `consume`, calling `f` once if the pattern matched.
+    fn apply_at<F: Fn()>(&'a self, reference_iter: &mut Reference, f: F) -> bool
+    where
+        Reference: PeekableExt,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let matched = self.consume(reference_iter);
+        if matched {
+            f();
+        }
+        matched
     }
-}
 
-impl<'a, T, Reference> Pattern<'a, Reference> for &'a [T]
-where
-    T: 'a,
-    Reference: Iterator<Item = &'a T> + PeekableExt,
-    T: Clone,
-    Reference::Item: Satisfies<&'a T>,
-{
-    type Iter = std::iter::Peekable<slice::Iter<'a, T>>;
-    type Dest = Vec<T>;
-    fn get_iter(&'a self) -> Self::Iter {
-        self.iter().peekable()
+    /// Consume items like `consume_with_dest`, handing the capture to `f` on a match
+    /// instead of storing it.
+    fn apply_with<F: Fn(Self::Dest)>(&'a self, reference_iter: &mut Reference, f: F) -> bool
+    where
+        Reference: PeekableExt,
+        Self::Dest: Destination<Reference::Item> + Default,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let temp = RefCell::new(Self::Dest::default());
+        let matched = self.consume_with_dest(reference_iter, Some(&temp));
+        if matched {
+            f(temp.into_inner());
+        }
+        matched
     }
-}
 
-impl<'a, T> Iterable<'a> for Vec<T>
-where
-    T: 'a,
-{
-    type Iter = std::iter::Peekable<slice::Iter<'a, T>>;
-    fn get_iter(&'a self) -> Self::Iter {
-        self.iter().peekable()
+    /// Lazily yield every position (in items) at which the pattern starts to match.
+    /// Each position is tried independently, so reported matches may overlap.
+    fn scan<'s, R>(&'a self, input: &'s R) -> Scan<'a, 's, Self, R>
+    where
+        R: Iterable<'s, Iter = Reference> + 's,
+    {
+        Scan {
+            pattern: self,
+            iter: Some(input.get_iter()),
+            position: 0,
+        }
     }
-}
 
-impl<'a, T, Reference> Pattern<'a, Reference> for Vec<T>
-where
-    T: 'a,
-    Reference: Iterator<Item = &'a T> + PeekableExt,
-    T: Clone,
-    Reference::Item: Satisfies<&'a T>,
-{
-    type Iter = std::iter::Peekable<slice::Iter<'a, T>>;
-    type Dest = Vec<T>;
-    fn get_iter(&'a self) -> Self::Iter {
-        self.iter().peekable()
+    /// Lazily yield every position at which the pattern matches, overlapping matches
+    /// included; the same iterator as `scan`. Each position clones the input and tries
+    /// the pattern afresh, so a full pass costs O(n * m) for input length n and match
+    /// cost m, which is O(n²) for patterns that may run to the end of the input.
+    fn window_matches<'s, R>(&'a self, input: &'s R) -> WindowMatches<'a, 's, Self, R>
+    where
+        R: Iterable<'s, Iter = Reference> + 's,
+    {
+        self.scan(input)
     }
-}
 
-/// A pattern that matches either of two sub-patterns.
-pub struct Or<A, B>(pub A, pub B);
+    /// Lazily yield the start of each match, resuming after the end of the previous one so
+    /// that matches never overlap. A match that consumes nothing advances by one item.
+    /// Positions after a match are skipped, so this is O(n * m) only in the worst case.
+    fn window_matches_non_overlapping<'s, R>(
+        &'a self,
+        input: &'s R,
+    ) -> NonOverlapping<'a, 's, Self, R>
+    where
+        R: Iterable<'s, Iter = Reference> + 's,
+    {
+        NonOverlapping {
+            pattern: self,
+            iter: Some(input.get_iter()),
+            position: 0,
+        }
+    }
 
-impl<'a, Reference, A, B, D> Pattern<'a, Reference> for Or<A, B>
-where
-    Reference: Iterator + Clone + PeekableExt,
-    A: Pattern<'a, Reference, Dest = D>,
-    B: Pattern<'a, Reference, Dest = D>,
-    D: Destination<Reference::Item> + Clone,
-    Reference::Item: Satisfies<<<A as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
-    Reference::Item: Satisfies<<<B as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
-{
-    type Iter = std::iter::Peekable<core::iter::Empty<Reference::Item>>;
-    type Dest = D;
+    /// Whether the input contains exactly `n` non-overlapping matches, counted as by
+    /// `window_matches_non_overlapping`. Stops looking once an `n + 1`th match is found.
+    fn matches_n<'s, R>(&'a self, input: &'s R, n: usize) -> bool
+    where
+        R: Iterable<'s, Iter = Reference> + 's,
+        Reference: Clone + PeekableExt,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        self.window_matches_non_overlapping(input)
+            .take(n.saturating_add(1))
+            .count()
+            == n
+    }
 
-    fn get_iter(&'a self) -> Self::Iter {
-        core::iter::empty().peekable()
+    /// Whether the input contains at least `n` non-overlapping matches, e.g.
+    /// `NUM.matches_at_least_n(&password, 2)`. Stops looking after the `n`th match.
+    fn matches_at_least_n<'s, R>(&'a self, input: &'s R, n: usize) -> bool
+    where
+        R: Iterable<'s, Iter = Reference> + 's,
+        Reference: Clone + PeekableExt,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        self.window_matches_non_overlapping(input).take(n).count() == n
     }
 
-    fn consume(&'a self, reference: &mut Reference) -> bool
+    /// Whether the input contains at most `n` non-overlapping matches. Stops looking
+    /// after an `n + 1`th match.
+    fn matches_at_most_n<'s, R>(&'a self, input: &'s R, n: usize) -> bool
     where
+        R: Iterable<'s, Iter = Reference> + 's,
+        Reference: Clone + PeekableExt,
+        Self::Dest: Destination<Reference::Item>,
         Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
     {
-        // Delegate to the more general `consume_with_dest`, avoiding
-        // duplicate snapshot/restore logic.
-        self.consume_with_dest(reference, None)
+        self.window_matches_non_overlapping(input)
+            .take(n.saturating_add(1))
+            .count()
+            <= n
     }
 
-    fn consume_with_dest(
+    /// Match the pattern against the reference, recording the outcome of each sub-pattern.
+    /// Trailing input left after the last sub-pattern is not reflected in the result.
+    fn match_detailed<'s, R>(
         &'a self,
-        reference: &mut Reference,
-        dest: Option<&RefCell<Self::Dest>>,
-    ) -> bool
+        reference: &'s R,
+    ) -> impl ResultHList + use<'a, 's, Reference, Self, R>
     where
+        R: Iterable<'s, Iter = Reference> + 's,
+        Self::Dest: Destination<Reference::Item>,
+        Reference: PeekableExt,
         Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
     {
-        let orig = reference.clone();
+        let mut iter = reference.get_iter();
+        self.consume_detailed(Some(&mut iter))
+    }
 
-        // Snapshot any provided dest value so we can restore on failure
-        let provided_backup = dest.as_ref().map(|d| d.borrow().clone());
+    /// Consume items like `consume`, recording the outcome of each sub-pattern.
+    /// Passing `None` records every sub-pattern as skipped without consuming anything.
+    fn consume_detailed(
+        &'a self,
+        reference_iter: Option<&mut Reference>,
+    ) -> impl ResultHList + use<'a, Reference, Self>
+    where
+        Reference: PeekableExt,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let result = match reference_iter {
+            Some(iter) => self.consume(iter).into(),
+            None => MatchResult::Skipped,
+        };
+        HCons(result, HNil)
+    }
 
-        // Try A: take a brief borrow to snapshot internal dest if available
-        let a_internal_backup = match self.0.get_dest_mut() {
-            Some(d) => {
-                let b = d.clone();
-                drop(d);
-                Some(b)
-            }
-            None => None,
-        };
+    /// Match the pattern at the start of the input and describe the attempt as a tree,
+    /// for tooling such as debuggers and test diagnostics. Trailing input is allowed, as
+    /// with `matches_prefix`. Nothing is captured while reporting.
+    #[cfg(feature = "debug-report")]
+    fn debug_match<'s, R>(&'a self, input: &'s R) -> DebugMatchReport
+    where
+        R: Iterable<'s, Iter = Reference> + 's,
+        Reference: PeekableExt + Clone,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item> + Clone + std::fmt::Debug,
+    {
+        self.consume_report(&mut input.get_iter())
+    }
 
-        if A::consume_with_dest(&self.0, reference, dest) {
-            return true;
+    /// Consume items like `consume`, describing the attempt for `debug_match`. Sequences,
+    /// alternatives and captures report their sub-patterns; other patterns are leaves.
+    #[cfg(feature = "debug-report")]
+    fn consume_report(&'a self, reference_iter: &mut Reference) -> DebugMatchReport
+    where
+        Reference: PeekableExt + Clone,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item> + Clone + std::fmt::Debug,
+    {
+        let mut trial = reference_iter.clone();
+        let succeeded = self.consume(&mut trial);
+        let consumed_items = reference_iter
+            .clone()
+            .take(consumed_between(reference_iter, &trial))
+            .map(|item| format!("{:?}", item))
+            .collect();
+        if succeeded {
+            *reference_iter = trial;
+        }
+        DebugMatchReport {
+            pattern_name: self.explain(),
+            succeeded,
+            consumed_items,
+            sub_reports: Vec::new(),
         }
+    }
 
-        *reference = orig.clone();
+    /// Match the whole reference in every possible way, returning each resulting capture.
+    /// `Or`, tuples and `..`/`..=` repetitions explore all of their alternatives; other
+    /// patterns contribute their single deterministic result.
+    #[cfg(feature = "ambiguous")]
+    fn matches_all<'s, R>(&'a self, reference: &'s R) -> Vec<Self::Dest>
+    where
+        R: Iterable<'s, Iter = Reference> + 's,
+        Reference: Clone + PeekableExt,
+        Self::Dest: Destination<Reference::Item> + Default + Clone,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        self.consume_all(&reference.get_iter(), Default::default())
+            .into_iter()
+            .filter_map(|(mut rest, dest)| rest.peek().is_none().then_some(dest))
+            .collect()
+    }
 
-        if let Some(b) = a_internal_backup {
-            if let Some(mut d) = self.0.get_dest_mut() {
-                *d = b.clone();
-            }
+    /// Consume items in every possible way, returning the remaining input and the capture
+    /// (built on top of `seed`) for each outcome.
+    #[cfg(feature = "ambiguous")]
+    fn consume_all(
+        &'a self,
+        reference: &Reference,
+        seed: Self::Dest,
+    ) -> Vec<(Reference, Self::Dest)>
+    where
+        Reference: Clone + PeekableExt,
+        Self::Dest: Destination<Reference::Item> + Clone,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut rest = reference.clone();
+        let dest = RefCell::new(seed);
+        if self.consume_with_dest(&mut rest, Some(&dest)) {
+            vec![(rest, dest.into_inner())]
+        } else {
+            Vec::new()
         }
+    }
 
-        if let Some(b) = provided_backup.clone() {
-            if let Some(dref) = dest {
-                *dref.borrow_mut() = b;
+    /// Every length of input prefix the pattern can match, in ascending order. Built on
+    /// `consume_all`, so every way of matching is considered; the input is not advanced.
+    #[cfg(feature = "analysis")]
+    fn all_prefix_lengths(&'a self, reference: &Reference) -> Vec<usize>
+    where
+        Reference: Clone + PeekableExt,
+        Self::Dest: Destination<Reference::Item> + Default + Clone,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut lengths: Vec<usize> = self
+            .consume_all(reference, Default::default())
+            .iter()
+            .map(|(rest, _)| consumed_between(reference, rest))
+            .collect();
+        lengths.sort_unstable();
+        lengths.dedup();
+        lengths
+    }
+
+    /// Replace the first match in `input` with `f` applied to the matched text.
+    fn replace_with<'s, R, F>(&'a self, input: &'s R, f: F) -> String
+    where
+        R: Iterable<'s, Iter = Reference> + 's,
+        F: Fn(&str) -> String,
+        Reference: Iterator<Item = char> + Clone + PeekableExt,
+        Self::Dest: Destination<char>,
+        char: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        replace_matches(self, input, f, 1)
+    }
+    /// Replace every non-overlapping match in `input` with `f` applied to the matched text.
+    fn replace_all_with<'s, R, F>(&'a self, input: &'s R, f: F) -> String
+    where
+        R: Iterable<'s, Iter = Reference> + 's,
+        F: Fn(&str) -> String,
+        Reference: Iterator<Item = char> + Clone + PeekableExt,
+        Self::Dest: Destination<char>,
+        char: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        replace_matches(self, input, f, usize::MAX)
+    }
+
+    /// Split `input` at the first `n` matches, returning at most `n + 1` segments; the
+    /// text after the `n`th match is kept whole as the last segment.
+    fn split_at_n<'s, R>(&'a self, input: &'s R, n: usize) -> Vec<String>
+    where
+        R: Iterable<'s, Iter = Reference> + 's,
+        Reference: Iterator<Item = char> + Clone + PeekableExt,
+        Self::Dest: Destination<char>,
+        char: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut segments = vec![String::new()];
+        walk_matches(self, input, n, |segment| match segment {
+            Segment::Unmatched(ch) => segments.last_mut().unwrap().push(ch),
+            Segment::Matched(_) => segments.push(String::new()),
+        });
+        segments
+    }
+
+    /// Chain a pattern chosen at runtime from this pattern's capture. If `f` returns
+    /// `None`, or the chosen pattern fails, nothing is consumed.
+    fn and_then<B, F>(self, f: F) -> AndThen<Self, B, F>
+    where
+        Self: Sized,
+        F: Fn(Self::Dest) -> Option<B>,
+    {
+        AndThen(self, f, PhantomData)
+    }
+
+    /// Call `f` with the reference iterator each time this pattern is about to consume
+    /// from it, leaving the outcome unchanged. Useful for tracing a pattern while debugging.
+    fn inspect<F>(self, f: F) -> Inspect<Self, F>
+    where
+        Self: Sized,
+        F: Fn(&Reference),
+    {
+        Inspect(self, f)
+    }
+
+    /// Log each attempt to match this pattern, with the next input item, and its outcome
+    /// under `label`. Logs go to stderr, or to `tracing` with the `tracing` feature.
+    fn trace(self, label: &'static str) -> Trace<Self>
+    where
+        Self: Sized,
+    {
+        Trace(self, label)
+    }
+
+    /// Repeat this pattern until `term` would match next, leaving `term` unconsumed.
+    fn repeat_until<Term>(self, term: Term) -> RepeatUntil<Self, Term>
+    where
+        Self: Sized,
+    {
+        RepeatUntil(self, term)
+    }
+}
+
+/// A piece of input visited by `walk_matches`.
+enum Segment {
+    Unmatched(char),
+    Matched(String),
+}
+
+/// Scan `input` left to right, reporting up to `limit` non-empty, non-overlapping matches
+/// and every character outside them, in order.
+fn walk_matches<'a, 's, P, Reference, R>(
+    pattern: &'a P,
+    input: &'s R,
+    limit: usize,
+    mut visit: impl FnMut(Segment),
+) where
+    P: Pattern<'a, Reference> + ?Sized,
+    R: Iterable<'s, Iter = Reference> + 's,
+    Reference: Iterator<Item = char> + Clone + PeekableExt,
+    P::Dest: Destination<char>,
+    char: Satisfies<<P::Iter as Iterator>::Item>,
+{
+    let mut iter = input.get_iter();
+    let mut found = 0;
+    while let Some(&ch) = iter.peek() {
+        if found < limit {
+            let mut trial = iter.clone();
+            if pattern.consume(&mut trial) {
+                let consumed = consumed_between(&iter, &trial);
+                if consumed > 0 {
+                    visit(Segment::Matched(iter.by_ref().take(consumed).collect()));
+                    found += 1;
+                    continue;
+                }
             }
         }
+        visit(Segment::Unmatched(ch));
+        iter.next();
+    }
+}
 
-        // Try B: snapshot (may be same underlying dest)
-        let b_internal_backup = match self.1.get_dest_mut() {
-            Some(d) => {
-                let b = d.clone();
-                drop(d);
-                Some(b)
-            }
-            None => None,
-        };
+/// Replace up to `limit` matches with `f` of the matched text, copying everything else.
+fn replace_matches<'a, 's, P, Reference, R, F>(
+    pattern: &'a P,
+    input: &'s R,
+    f: F,
+    limit: usize,
+) -> String
+where
+    P: Pattern<'a, Reference> + ?Sized,
+    R: Iterable<'s, Iter = Reference> + 's,
+    F: Fn(&str) -> String,
+    Reference: Iterator<Item = char> + Clone + PeekableExt,
+    P::Dest: Destination<char>,
+    char: Satisfies<<P::Iter as Iterator>::Item>,
+{
+    let mut out = String::new();
+    walk_matches(pattern, input, limit, |segment| match segment {
+        Segment::Unmatched(ch) => out.push(ch),
+        Segment::Matched(text) => out.push_str(&f(&text)),
+    });
+    out
+}
 
-        if B::consume_with_dest(&self.1, reference, dest) {
-            return true;
-        }
+/// An iterator over the positions at which a pattern starts to match, created by `Pattern::scan`.
+pub struct Scan<'a, 's, P, R>
+where
+    P: ?Sized,
+    R: Iterable<'s> + 's,
+{
+    pattern: &'a P,
+    iter: Option<R::Iter>,
+    position: usize,
+}
 
-        *reference = orig;
+impl<'a, 's, P, R> Iterator for Scan<'a, 's, P, R>
+where
+    P: Pattern<'a, R::Iter> + ?Sized,
+    R: Iterable<'s> + 's,
+    R::Iter: Clone + PeekableExt,
+    P::Dest: Destination<<R::Iter as Iterator>::Item>,
+    <R::Iter as Iterator>::Item: Satisfies<<P::Iter as Iterator>::Item>,
+{
+    type Item = usize;
 
-        if let Some(b) = b_internal_backup {
-            if let Some(mut d) = self.1.get_dest_mut() {
-                *d = b;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let iter = self.iter.as_mut()?;
+            let position = self.position;
+            let matched = self.pattern.consume(&mut iter.clone());
+            // Advance one item; the end of input is tried once before stopping
+            if iter.next().is_none() {
+                self.iter = None;
+            } else {
+                self.position += 1;
+            }
+            if matched {
+                return Some(position);
             }
         }
+    }
+}
 
-        if let Some(b) = provided_backup {
-            if let Some(dref) = dest {
-                *dref.borrow_mut() = b;
+/// An iterator over every, possibly overlapping, match position, created by
+/// `Pattern::window_matches`.
+pub type WindowMatches<'a, 's, P, R> = Scan<'a, 's, P, R>;
+
+/// An iterator over the start positions of non-overlapping matches, created by
+/// `Pattern::window_matches_non_overlapping`.
+pub struct NonOverlapping<'a, 's, P, R>
+where
+    P: ?Sized,
+    R: Iterable<'s> + 's,
+{
+    pattern: &'a P,
+    iter: Option<R::Iter>,
+    position: usize,
+}
+
+impl<'a, 's, P, R> Iterator for NonOverlapping<'a, 's, P, R>
+where
+    P: Pattern<'a, R::Iter> + ?Sized,
+    R: Iterable<'s> + 's,
+    R::Iter: Clone + PeekableExt,
+    P::Dest: Destination<<R::Iter as Iterator>::Item>,
+    <R::Iter as Iterator>::Item: Satisfies<<P::Iter as Iterator>::Item>,
+{
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let iter = self.iter.as_mut()?;
+            let position = self.position;
+            let mut trial = iter.clone();
+            let matched = self.pattern.consume(&mut trial);
+            let consumed = if matched {
+                consumed_between(iter, &trial)
+            } else {
+                0
+            };
+            if consumed > 0 {
+                // Resume right after the match
+                *iter = trial;
+                self.position += consumed;
+            } else if iter.next().is_none() {
+                // The end of input is tried once before stopping
+                self.iter = None;
+            } else {
+                self.position += 1;
+            }
+            if matched {
+                return Some(position);
             }
         }
+    }
+}
 
-        false
+impl<'a> Iterable<'a> for &'a str {
+    type Iter = Checkpoint<std::iter::Peekable<Chars<'a>>>;
+    fn get_iter(&'a self) -> Self::Iter {
+        Checkpoint::new(self.chars().peekable())
     }
 }
 
-impl<'a, Reference, A, B, DA, DB> Pattern<'a, Reference> for (A, B)
+impl<'a, 'b: 'a, Reference> Pattern<'a, Reference> for &'b str
 where
-    Reference: Iterator + PeekableExt,
-    A: Pattern<'a, Reference, Dest = DA>,
-    B: Pattern<'a, Reference, Dest = DB>,
-    DA: Destination<Reference::Item> + Clone,
-    DB: Destination<Reference::Item> + Clone,
-    Reference::Item: Satisfies<<<A as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
-    Reference::Item: Satisfies<<<B as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
-    Reference::Item: Clone,
+    Reference: Iterator<Item = char> + PeekableExt,
 {
-    type Iter = std::iter::Peekable<core::iter::Empty<Reference::Item>>;
-    type Dest = (DA, DB);
+    type Iter = Checkpoint<std::iter::Peekable<Chars<'a>>>;
+    type Dest = String;
+    fn get_iter(&'a self) -> Self::Iter {
+        Checkpoint::new(self.chars().peekable())
+    }
+
+    fn explain(&self) -> String {
+        format!("\"{}\"", self)
+    }
+}
 
+impl<'a> Iterable<'a> for String {
+    type Iter = Checkpoint<std::iter::Peekable<Chars<'a>>>;
     fn get_iter(&'a self) -> Self::Iter {
-        core::iter::empty().peekable()
+        Checkpoint::new(self.chars().peekable())
     }
+}
 
-    fn consume(&'a self, reference: &mut Reference) -> bool
+impl<'a, Reference> Pattern<'a, Reference> for String
+where
+    Reference: Iterator<Item = char> + PeekableExt,
+{
+    type Iter = Checkpoint<std::iter::Peekable<Chars<'a>>>;
+    type Dest = String;
+    fn get_iter(&'a self) -> Self::Iter {
+        Checkpoint::new(self.chars().peekable())
+    }
+
+    fn explain(&self) -> String {
+        format!("\"{}\"", self)
+    }
+}
+
+/// Both variants are read through a borrow of the `Cow` itself, so the iterator lives
+/// as long as that borrow and an owned string never needs to be boxed or cloned.
+impl<'a> Iterable<'a> for Cow<'_, str> {
+    type Iter = Checkpoint<std::iter::Peekable<Chars<'a>>>;
+    fn get_iter(&'a self) -> Self::Iter {
+        Checkpoint::new(self.chars().peekable())
+    }
+}
+
+impl<'a, T> Iterable<'a> for &'a [T]
+where
+    T: 'a,
+{
+    type Iter = std::iter::Peekable<slice::Iter<'a, T>>;
+    fn get_iter(&'a self) -> Self::Iter {
+        self.iter().peekable()
+    }
+}
+
+impl<'a, T, Reference> Pattern<'a, Reference> for &'a [T]
+where
+    T: 'a,
+    Reference: Iterator<Item = &'a T> + PeekableExt,
+    T: Clone,
+    Reference::Item: Satisfies<&'a T>,
+{
+    type Iter = std::iter::Peekable<slice::Iter<'a, T>>;
+    type Dest = Vec<T>;
+    fn get_iter(&'a self) -> Self::Iter {
+        self.iter().peekable()
+    }
+
+    fn explain(&self) -> String {
+        format!("[{} items]", self.len())
+    }
+}
+
+/// Matches a single character that appears anywhere in the slice, the runtime
+/// counterpart of `AnyOf<char, N>` for character classes only known while the program
+/// runs. This is a character set, not a sequence: `class.as_slice().consume(..)` with
+/// `class = vec!['a', 'b']` accepts one `'a'` or one `'b'`. It is implemented for the
+/// unsized `[char]` because `&[T]` already matches its items one after another, in order,
+/// against a reference over `&T`.
+impl<'a, Reference> Pattern<'a, Reference> for [char]
+where
+    Reference: Iterator<Item = char> + PeekableExt,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = char;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("any of {:?}", self)
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
     where
         Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
     {
-        self.0.consume(reference) && self.1.consume(reference)
+        consume_char_if(reference, dest, |ch| self.contains(ch))
+    }
+}
+
+impl<'a, T> Iterable<'a> for Vec<T>
+where
+    T: 'a,
+{
+    type Iter = std::iter::Peekable<slice::Iter<'a, T>>;
+    fn get_iter(&'a self) -> Self::Iter {
+        self.iter().peekable()
+    }
+}
+
+impl<'a, T, Reference> Pattern<'a, Reference> for Vec<T>
+where
+    T: 'a,
+    Reference: Iterator<Item = &'a T> + PeekableExt,
+    T: Clone,
+    Reference::Item: Satisfies<&'a T>,
+{
+    type Iter = std::iter::Peekable<slice::Iter<'a, T>>;
+    type Dest = Vec<T>;
+    fn get_iter(&'a self) -> Self::Iter {
+        self.iter().peekable()
+    }
+
+    fn explain(&self) -> String {
+        format!("[{} items]", self.len())
+    }
+}
+
+/// An optional pattern: `Some(p)` matches like `p`, while `None` always matches
+/// without consuming anything. Handy for pattern parts chosen at runtime.
+impl<'a, Reference, P, D> Pattern<'a, Reference> for Option<P>
+where
+    Reference: Iterator + PeekableExt,
+    P: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item> + Default + Clone,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = Option<D>;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        match self {
+            Some(p) => p.explain(),
+            None => "nothing".to_string(),
+        }
+    }
+
+    fn matches_empty(&self) -> bool {
+        self.as_ref().is_none_or(|p| p.matches_empty())
     }
 
     fn consume_with_dest(
         &'a self,
-        reference_iter: &mut Reference,
+        reference: &mut Reference,
         dest: Option<&RefCell<Self::Dest>>,
     ) -> bool
     where
         Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
     {
-        if let Some(dref) = dest {
-            // snapshot current tuple dest
-            let snapshot = dref.borrow().clone();
-            // create inner temp dests
-            let a_temp = RefCell::new(snapshot.0);
-            let b_temp = RefCell::new(snapshot.1);
+        let Some(p) = self else {
+            return true;
+        };
+        let Some(dref) = dest else {
+            return p.consume(reference);
+        };
+
+        // Seed from the current capture so repeated matches accumulate; on failure the
+        // capture is left exactly as it was
+        let previous = dref.borrow().clone();
+        let temp = RefCell::new(previous.unwrap_or_default());
+        let matched = P::consume_with_dest(p, reference, Some(&temp));
+        if matched {
+            *dref.borrow_mut() = Some(temp.into_inner());
+        }
+        matched
+    }
+}
+
+impl<Item, D> Destination<Item> for Option<D>
+where
+    D: Destination<Item>,
+{
+    fn pickup(&mut self, item: Item) {
+        if let Some(d) = self {
+            d.pickup(item);
+        }
+    }
+}
+
+/// A pattern that matches `P` if it can and succeeds either way, written `p?` in
+/// `matches!`. When `P` does not match nothing is consumed and the capture is `None`.
+pub struct Opt<P>(pub P);
+
+impl<'a, Reference, P, D> Pattern<'a, Reference> for Opt<P>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    P: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item> + Default,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = Option<D>;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("({})?", self.0.explain())
+    }
+
+    fn matches_empty(&self) -> bool {
+        true
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        let inner = RefCell::new(D::default());
+        let matched = P::consume_with_dest(&self.0, &mut trial, dest.map(|_| &inner));
+        if matched {
+            *reference = trial;
+        }
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = matched.then(|| inner.into_inner());
+        }
+        true
+    }
+}
+
+/// A token tree yielded when iterating a token stream. Token trees have no `PartialEq`
+/// of their own, so two items are equal when their textual forms are.
+#[cfg(feature = "proc-macro2")]
+#[derive(Clone, Debug)]
+pub struct TokenTreeItem(pub proc_macro2::TokenTree);
+
+#[cfg(feature = "proc-macro2")]
+impl PartialEq for TokenTreeItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_string() == other.0.to_string()
+    }
+}
+
+#[cfg(feature = "proc-macro2")]
+pub type TokenStreamIter = Checkpoint<
+    std::iter::Map<
+        proc_macro2::token_stream::IntoIter,
+        fn(proc_macro2::TokenTree) -> TokenTreeItem,
+    >,
+>;
+
+#[cfg(feature = "proc-macro2")]
+impl<'a> Iterable<'a> for proc_macro2::TokenStream {
+    type Iter = TokenStreamIter;
+    fn get_iter(&'a self) -> Self::Iter {
+        let wrap: fn(proc_macro2::TokenTree) -> TokenTreeItem = TokenTreeItem;
+        Checkpoint::new(self.clone().into_iter().map(wrap))
+    }
+}
+
+/// The token stream iterator cannot peek on its own, so peeked tokens are buffered
+/// in the checkpoint's front queue until they are consumed.
+#[cfg(feature = "proc-macro2")]
+impl PeekableExt for TokenStreamIter {
+    fn peek(&mut self) -> Option<&Self::Item> {
+        if self.front.is_empty() {
+            // Only `next` records to the trail; a peeked token is not consumed yet.
+            let next = self.inner.next()?;
+            self.front.push_back(next);
+            let buffered = self.front.len();
+            self.record(|stats| {
+                stats.items_buffered_peak = stats.items_buffered_peak.max(buffered)
+            });
+        }
+        self.front.front()
+    }
+}
+
+#[cfg(feature = "proc-macro2")]
+impl<T> Destination<TokenTreeItem> for Vec<T> {}
+
+/// A pattern that matches either of two sub-patterns.
+pub struct Or<A, B>(pub A, pub B);
+
+impl<'a, Reference, A, B, D> Pattern<'a, Reference> for Or<A, B>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    A: Pattern<'a, Reference, Dest = D>,
+    B: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item> + Clone,
+    Reference::Item: Satisfies<<<A as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+    Reference::Item: Satisfies<<<B as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = std::iter::Peekable<core::iter::Empty<Reference::Item>>;
+    type Dest = D;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty().peekable()
+    }
+
+    fn explain(&self) -> String {
+        format!("({} | {})", self.0.explain(), self.1.explain())
+    }
+
+    #[cfg(feature = "debug-report")]
+    fn consume_report(&'a self, reference: &mut Reference) -> DebugMatchReport
+    where
+        Reference: PeekableExt + Clone,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item> + Clone + std::fmt::Debug,
+    {
+        let mut trial = reference.clone();
+        let first = self.0.consume_report(&mut trial);
+        let mut sub_reports = vec![first];
+        if !sub_reports[0].succeeded {
+            trial = reference.clone();
+            sub_reports.push(self.1.consume_report(&mut trial));
+        }
+        let last = sub_reports.last().unwrap();
+        let (succeeded, consumed_items) = (last.succeeded, last.consumed_items.clone());
+        if succeeded {
+            *reference = trial;
+        }
+        DebugMatchReport {
+            pattern_name: self.explain(),
+            succeeded,
+            consumed_items,
+            sub_reports,
+        }
+    }
+
+    /// Reports whichever alternative got further into the input.
+    fn try_consume_err(
+        &'a self,
+        reference: &mut Reference,
+    ) -> Result<(), PatternError<Reference::Item>>
+    where
+        Reference: PeekableExt + Clone,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item> + Clone,
+    {
+        if self.consume(reference) {
+            return Ok(());
+        }
+        let first = self.0.try_consume_err(&mut reference.clone()).err();
+        let second = self.1.try_consume_err(&mut reference.clone()).err();
+        let error = match (first, second) {
+            (Some(a), Some(b)) => {
+                if b.position > a.position {
+                    b
+                } else {
+                    a
+                }
+            }
+            (Some(e), None) | (None, Some(e)) => e,
+            (None, None) => PatternError::at(&mut reference.clone(), 0),
+        };
+        Err(error.within(self.explain()))
+    }
+
+    fn matches_empty(&self) -> bool {
+        self.0.matches_empty() || self.1.matches_empty()
+    }
+
+    #[cfg(feature = "ambiguous")]
+    fn consume_all(
+        &'a self,
+        reference: &Reference,
+        seed: Self::Dest,
+    ) -> Vec<(Reference, Self::Dest)>
+    where
+        Reference: Clone + PeekableExt,
+        Self::Dest: Destination<Reference::Item> + Clone,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut outcomes = self.0.consume_all(reference, seed.clone());
+        outcomes.extend(self.1.consume_all(reference, seed));
+        outcomes
+    }
+
+    fn consume(&'a self, reference: &mut Reference) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        // Delegate to the more general `consume_with_dest`, avoiding
+        // duplicate snapshot/restore logic.
+        self.consume_with_dest(reference, None)
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let orig = reference.clone();
+
+        // Snapshot the provided dest and both internal dests up front. The
+        // branches may share the same underlying `RefCell` (e.g. two `To`s
+        // writing into one `Dest`), so every attempt must start from the
+        // state observed before `Or` ran, not from what the previous branch
+        // left behind.
+        let provided_backup = dest.as_ref().map(|d| d.borrow().clone());
+        let a_internal_backup = self.0.get_dest_mut().map(|d| d.clone());
+        let b_internal_backup = self.1.get_dest_mut().map(|d| d.clone());
+
+        let restore = |reference: &mut Reference| {
+            *reference = orig.clone();
+            if let Some(b) = &b_internal_backup
+                && let Some(mut d) = self.1.get_dest_mut()
+            {
+                *d = b.clone();
+            }
+            if let Some(b) = &a_internal_backup
+                && let Some(mut d) = self.0.get_dest_mut()
+            {
+                *d = b.clone();
+            }
+            if let Some(b) = &provided_backup
+                && let Some(dref) = dest
+            {
+                *dref.borrow_mut() = b.clone();
+            }
+        };
+
+        if A::consume_with_dest(&self.0, reference, dest) {
+            return true;
+        }
+        restore(reference);
+
+        if B::consume_with_dest(&self.1, reference, dest) {
+            return true;
+        }
+        restore(reference);
+
+        false
+    }
+}
+
+/// A one-element tuple matches exactly like its element and shares its capture.
+impl<'a, Reference, A, D> Pattern<'a, Reference> for (A,)
+where
+    Reference: Iterator + PeekableExt,
+    A: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item>,
+{
+    type Iter = A::Iter;
+    type Dest = D;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        self.0.get_iter()
+    }
+
+    fn explain(&self) -> String {
+        self.0.explain()
+    }
+
+    fn matches_empty(&self) -> bool {
+        self.0.matches_empty()
+    }
+
+    fn get_dest_mut(&self) -> Option<std::cell::RefMut<'_, Self::Dest>> {
+        self.0.get_dest_mut()
+    }
+
+    fn consume(&'a self, reference: &mut Reference) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        self.0.consume(reference)
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        self.0.consume_with_dest(reference, dest)
+    }
+
+    #[cfg(feature = "debug-report")]
+    fn consume_report(&'a self, reference: &mut Reference) -> DebugMatchReport
+    where
+        Reference: PeekableExt + Clone,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item> + Clone + std::fmt::Debug,
+    {
+        self.0.consume_report(reference)
+    }
+
+    #[cfg(feature = "ambiguous")]
+    fn consume_all(
+        &'a self,
+        reference: &Reference,
+        seed: Self::Dest,
+    ) -> Vec<(Reference, Self::Dest)>
+    where
+        Reference: Clone + PeekableExt,
+        Self::Dest: Destination<Reference::Item> + Clone,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        self.0.consume_all(reference, seed)
+    }
+}
+
+impl<'a, Reference, A, B, DA, DB> Pattern<'a, Reference> for (A, B)
+where
+    Reference: Iterator + PeekableExt,
+    A: Pattern<'a, Reference, Dest = DA>,
+    B: Pattern<'a, Reference, Dest = DB>,
+    DA: Destination<Reference::Item> + Clone,
+    DB: Destination<Reference::Item> + Clone,
+    Reference::Item: Satisfies<<<A as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+    Reference::Item: Satisfies<<<B as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+    Reference::Item: Clone,
+{
+    type Iter = std::iter::Peekable<core::iter::Empty<Reference::Item>>;
+    type Dest = (DA, DB);
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty().peekable()
+    }
+
+    fn explain(&self) -> String {
+        format!("{} then {}", self.0.explain(), self.1.explain())
+    }
+
+    fn try_consume_err(
+        &'a self,
+        reference: &mut Reference,
+    ) -> Result<(), PatternError<Reference::Item>>
+    where
+        Reference: PeekableExt + Clone,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item> + Clone,
+    {
+        let mut trial = reference.clone();
+        if self.consume(&mut trial) {
+            *reference = trial;
+            return Ok(());
+        }
+        let mut trial = reference.clone();
+        let error = match self.0.try_consume_err(&mut trial) {
+            Err(error) => error,
+            Ok(()) => {
+                let offset = consumed_between(reference, &trial);
+                match self.1.try_consume_err(&mut trial) {
+                    Err(error) => error.offset(offset),
+                    // The parts match one by one, but not together, e.g. after a `Lazy`
+                    Ok(()) => PatternError::at(&mut reference.clone(), 0),
+                }
+            }
+        };
+        Err(error.within(self.explain()))
+    }
+
+    /// Reports each part matched in turn. A `Lazy` first part is reported with the
+    /// match it tries first, without retrying it for the second part.
+    #[cfg(feature = "debug-report")]
+    fn consume_report(&'a self, reference: &mut Reference) -> DebugMatchReport
+    where
+        Reference: PeekableExt + Clone,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item> + Clone + std::fmt::Debug,
+    {
+        let mut trial = reference.clone();
+        let mut sub_reports = vec![self.0.consume_report(&mut trial)];
+        if sub_reports[0].succeeded {
+            sub_reports.push(self.1.consume_report(&mut trial));
+        }
+        let succeeded = sub_reports.len() == 2 && sub_reports[1].succeeded;
+        if succeeded {
+            *reference = trial;
+        }
+        DebugMatchReport::sequence(self.explain(), succeeded, sub_reports)
+    }
+
+    fn matches_empty(&self) -> bool {
+        self.0.matches_empty() && self.1.matches_empty()
+    }
+
+    fn consume(&'a self, reference: &mut Reference) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        self.0
+            .consume_with_continuation(reference, None, &self.1, None)
+    }
+
+    #[cfg(feature = "ambiguous")]
+    fn consume_all(
+        &'a self,
+        reference: &Reference,
+        seed: Self::Dest,
+    ) -> Vec<(Reference, Self::Dest)>
+    where
+        Reference: Clone + PeekableExt,
+        Self::Dest: Destination<Reference::Item> + Clone,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let (seed_a, seed_b) = seed;
+        self.0
+            .consume_all(reference, seed_a)
+            .into_iter()
+            .flat_map(|(rest, a)| {
+                self.1
+                    .consume_all(&rest, seed_b.clone())
+                    .into_iter()
+                    .map(move |(rest, b)| (rest, (a.clone(), b)))
+            })
+            .collect()
+    }
+
+    fn consume_detailed(
+        &'a self,
+        mut reference_iter: Option<&mut Reference>,
+    ) -> impl ResultHList + use<'a, Reference, A, B, DA, DB>
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let head = self.0.consume_detailed(reference_iter.as_deref_mut());
+        // Once a sub-pattern fails, the rest are recorded as skipped
+        let tail = if head.is_matched() {
+            self.1.consume_detailed(reference_iter)
+        } else {
+            self.1.consume_detailed(None)
+        };
+        head.append(tail)
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference_iter: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        if let Some(dref) = dest {
+            // snapshot current tuple dest
+            let snapshot = dref.borrow().clone();
+            // create inner temp dests
+            let a_temp = RefCell::new(snapshot.0);
+            let b_temp = RefCell::new(snapshot.1);
+
+            // try to consume both parts routing to temp dests
+            if A::consume_with_continuation(
+                &self.0,
+                reference_iter,
+                Some(&a_temp),
+                &self.1,
+                Some(&b_temp),
+            ) {
+                // commit back into original dest
+                let mut d = dref.borrow_mut();
+                d.0 = a_temp.into_inner();
+                d.1 = b_temp.into_inner();
+                return true;
+            }
+            return false;
+        }
+
+        self.consume(reference_iter)
+    }
+}
+
+/// Borrows a pattern so the elements after the first in a tuple can be matched as a
+/// tuple of their own.
+struct PatternRef<'p, P>(&'p P);
+
+impl<'c, 'p, Reference, P> Pattern<'c, Reference> for PatternRef<'p, P>
+where
+    Reference: Iterator,
+    P: Pattern<'p, Reference>,
+{
+    type Iter = P::Iter;
+    type Dest = P::Dest;
+
+    fn get_iter(&'c self) -> Self::Iter {
+        self.0.get_iter()
+    }
+
+    fn explain(&self) -> String {
+        self.0.explain()
+    }
+
+    fn get_dest_mut(&self) -> Option<std::cell::RefMut<'_, Self::Dest>> {
+        self.0.get_dest_mut()
+    }
+
+    fn matches_empty(&self) -> bool {
+        self.0.matches_empty()
+    }
+
+    fn consume(&'c self, reference: &mut Reference) -> bool
+    where
+        Reference: PeekableExt,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        self.0.consume(reference)
+    }
+
+    fn consume_with_dest(
+        &'c self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference: PeekableExt,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        self.0.consume_with_dest(reference, dest)
+    }
+
+    fn consume_with_continuation<'d, C>(
+        &'c self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+        continuation: &'d C,
+        continuation_dest: Option<&RefCell<C::Dest>>,
+    ) -> bool
+    where
+        Reference: PeekableExt,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+        C: Pattern<'d, Reference>,
+        C::Dest: Destination<Reference::Item> + Clone,
+        Reference::Item: Satisfies<<C::Iter as Iterator>::Item>,
+    {
+        self.0
+            .consume_with_continuation(reference, dest, continuation, continuation_dest)
+    }
+}
+
+/// Implements `Pattern` and `Destination` for an N-tuple of sub-patterns.
+/// Unlike the pair impl, the whole tuple rolls back if any element fails.
+macro_rules! impl_pattern_tuple {
+    ($head:tt, $($rest:tt),+) => {
+        impl_pattern_tuple!(@impl [$head, $($rest),+] $head [$($rest),+]);
+    };
+    (
+        @impl [$(($P:ident, $D:ident, $idx:tt)),+]
+        ($P0:ident, $D0:ident, $idx0:tt) [$(($PR:ident, $DR:ident, $idxr:tt)),+]
+    ) => {
+        impl<'a, Reference, $($P, $D),+> Pattern<'a, Reference> for ($($P,)+)
+        where
+            Reference: Iterator + Clone + PeekableExt,
+            $(
+                $P: Pattern<'a, Reference, Dest = $D>,
+                $D: Destination<Reference::Item> + Clone,
+                Reference::Item: Satisfies<<<$P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+            )+
+            Reference::Item: Clone,
+        {
+            type Iter = core::iter::Empty<Reference::Item>;
+            type Dest = ($($D,)+);
+
+            fn get_iter(&'a self) -> Self::Iter {
+                core::iter::empty()
+            }
+
+            fn explain(&self) -> String {
+                [$(self.$idx.explain()),+].join(" then ")
+            }
+
+            fn matches_empty(&self) -> bool {
+                $(self.$idx.matches_empty())&&+
+            }
+
+            #[cfg(feature = "debug-report")]
+            fn consume_report(&'a self, reference: &mut Reference) -> DebugMatchReport
+            where
+                Reference: PeekableExt + Clone,
+                Self::Dest: Destination<Reference::Item>,
+                Reference::Item: Satisfies<<Self::Iter as Iterator>::Item> + Clone + std::fmt::Debug,
+            {
+                let mut trial = reference.clone();
+                let mut sub_reports: Vec<DebugMatchReport> = Vec::new();
+                $(
+                    if sub_reports.last().is_none_or(|report| report.succeeded) {
+                        sub_reports.push(self.$idx.consume_report(&mut trial));
+                    }
+                )+
+                let succeeded = sub_reports.len() == [$($idx),+].len()
+                    && sub_reports.iter().all(|report| report.succeeded);
+                if succeeded {
+                    *reference = trial;
+                }
+                DebugMatchReport::sequence(self.explain(), succeeded, sub_reports)
+            }
+
+            #[cfg(feature = "ambiguous")]
+            fn consume_all(
+                &'a self,
+                reference: &Reference,
+                seed: Self::Dest,
+            ) -> Vec<(Reference, Self::Dest)>
+            where
+                Reference: Clone + PeekableExt,
+                Self::Dest: Destination<Reference::Item> + Clone,
+                Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+            {
+                let mut states = vec![(reference.clone(), seed)];
+                $(
+                    states = states
+                        .into_iter()
+                        .flat_map(|(iter, dest)| {
+                            $P::consume_all(&self.$idx, &iter, dest.$idx.clone())
+                                .into_iter()
+                                .map(move |(rest, d)| {
+                                    let mut dest = dest.clone();
+                                    dest.$idx = d;
+                                    (rest, dest)
+                                })
+                        })
+                        .collect();
+                )+
+                states
+            }
+
+            fn consume_with_dest(
+                &'a self,
+                reference: &mut Reference,
+                dest: Option<&RefCell<Self::Dest>>,
+            ) -> bool
+            where
+                Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+            {
+                let mut trial = reference.clone();
+                // The other elements are the first one's continuation, so a `Lazy` first
+                // element retries until they match
+                let rest = ($(PatternRef(&self.$idxr),)+);
+                match dest {
+                    Some(dref) => {
+                        // Route the first element and the rest into temp dests seeded from
+                        // the current value
+                        let snapshot = dref.borrow().clone();
+                        let head = RefCell::new(snapshot.$idx0);
+                        let tail = RefCell::new(($(snapshot.$idxr,)+));
+                        if !$P0::consume_with_continuation(
+                            &self.$idx0,
+                            &mut trial,
+                            Some(&head),
+                            &rest,
+                            Some(&tail),
+                        ) {
+                            return false;
+                        }
+                        *reference = trial;
+                        #[allow(non_snake_case)]
+                        let ($($DR,)+) = tail.into_inner();
+                        *dref.borrow_mut() = (head.into_inner(), $($DR,)+);
+                        true
+                    }
+                    None => {
+                        if !$P0::consume_with_continuation(&self.$idx0, &mut trial, None, &rest, None) {
+                            return false;
+                        }
+                        *reference = trial;
+                        true
+                    }
+                }
+            }
+        }
+
+        impl<Item, $($D),+> Destination<Item> for ($($D,)+)
+        where
+            $($D: Destination<Item>,)+
+            Item: Clone,
+        {
+            fn pickup(&mut self, item: Item) {
+                $(self.$idx.pickup(item.clone());)+
+            }
+        }
+    };
+}
+
+impl_pattern_tuple!((A, DA, 0), (B, DB, 1), (C, DC, 2));
+impl_pattern_tuple!((A, DA, 0), (B, DB, 1), (C, DC, 2), (E, DE, 3));
+impl_pattern_tuple!((A, DA, 0), (B, DB, 1), (C, DC, 2), (E, DE, 3), (F, DF, 4));
+impl_pattern_tuple!(
+    (A, DA, 0),
+    (B, DB, 1),
+    (C, DC, 2),
+    (E, DE, 3),
+    (F, DF, 4),
+    (G, DG, 5)
+);
+impl_pattern_tuple!(
+    (A, DA, 0),
+    (B, DB, 1),
+    (C, DC, 2),
+    (E, DE, 3),
+    (F, DF, 4),
+    (G, DG, 5),
+    (H, DH, 6)
+);
+impl_pattern_tuple!(
+    (A, DA, 0),
+    (B, DB, 1),
+    (C, DC, 2),
+    (E, DE, 3),
+    (F, DF, 4),
+    (G, DG, 5),
+    (H, DH, 6),
+    (J, DJ, 7)
+);
+
+/// A pattern that matches a prefix pattern, then another pattern, capturing only the latter.
+pub struct Preceded<Pre, P>(pub Pre, pub P);
+
+impl<'a, Reference, Pre, P, D> Pattern<'a, Reference> for Preceded<Pre, P>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    Pre: Pattern<'a, Reference>,
+    P: Pattern<'a, Reference, Dest = D>,
+    Pre::Dest: Destination<Reference::Item>,
+    D: Destination<Reference::Item> + Clone,
+    Reference::Item: Satisfies<<<Pre as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = D;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("{} then {}", self.0.explain(), self.1.explain())
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        if !Pre::consume(&self.0, &mut trial) {
+            return false;
+        }
+        // Snapshot the provided dest so a partial match leaves it untouched
+        let backup = dest.map(|d| d.borrow().clone());
+        if P::consume_with_dest(&self.1, &mut trial, dest) {
+            *reference = trial;
+            return true;
+        }
+        if let (Some(dref), Some(b)) = (dest, backup) {
+            *dref.borrow_mut() = b;
+        }
+        false
+    }
+}
+
+/// A pattern that matches a pattern, then a suffix pattern, capturing only the former.
+pub struct Followed<P, Post>(pub P, pub Post);
+
+impl<'a, Reference, P, Post, D> Pattern<'a, Reference> for Followed<P, Post>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    P: Pattern<'a, Reference, Dest = D>,
+    Post: Pattern<'a, Reference>,
+    D: Destination<Reference::Item> + Clone,
+    Post::Dest: Destination<Reference::Item>,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Satisfies<<<Post as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = D;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("{} then {}", self.0.explain(), self.1.explain())
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        // Snapshot the provided dest so a partial match leaves it untouched
+        let backup = dest.map(|d| d.borrow().clone());
+        if P::consume_with_dest(&self.0, &mut trial, dest) && Post::consume(&self.1, &mut trial) {
+            *reference = trial;
+            return true;
+        }
+        if let (Some(dref), Some(b)) = (dest, backup) {
+            *dref.borrow_mut() = b;
+        }
+        false
+    }
+}
+
+/// A pattern that matches like `P` but captures nothing, so patterns with different
+/// destinations can be combined where only matching matters.
+pub struct Discard<P>(pub P);
+
+impl<'a, Reference, P> Pattern<'a, Reference> for Discard<P>
+where
+    Reference: Iterator + PeekableExt,
+    P: Pattern<'a, Reference>,
+    P::Dest: Destination<Reference::Item>,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = ();
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        self.0.explain()
+    }
+
+    fn matches_empty(&self) -> bool {
+        self.0.matches_empty()
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        _dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        self.0.consume(reference)
+    }
+}
+
+/// A pattern that matches the characters of a literal pattern such as `"abc"` ignoring
+/// case. The capture receives the input's characters, in their original case.
+pub struct CaseInsensitive<P>(pub P);
+
+impl<'a, Reference, P> Pattern<'a, Reference> for CaseInsensitive<P>
+where
+    Reference: Iterator<Item = char> + Clone + PeekableExt,
+    P: Pattern<'a, Reference>,
+    P::Iter: Iterator<Item = char>,
+    P::Dest: Destination<char>,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = P::Dest;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("(?i){}", self.0.explain())
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        let mut consumed = Vec::new();
+        for pat_ch in self.0.get_iter() {
+            match trial.next() {
+                Some(ch) if ch == pat_ch || ch.to_lowercase().eq(pat_ch.to_lowercase()) => {
+                    consumed.push(ch)
+                }
+                _ => return false,
+            }
+        }
+        *reference = trial;
+        if let Some(dref) = dest {
+            let mut d = dref.borrow_mut();
+            consumed.into_iter().for_each(|ch| d.pickup(ch));
+        }
+        true
+    }
+}
+
+/// A pattern that tries both sub-patterns and keeps whichever consumes more input.
+/// Ties go to the first sub-pattern.
+pub struct Longest<A, B>(pub A, pub B);
+
+impl<'a, Reference, A, B, D> Pattern<'a, Reference> for Longest<A, B>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    A: Pattern<'a, Reference, Dest = D>,
+    B: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item> + Clone,
+    Reference::Item: Satisfies<<<A as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Satisfies<<<B as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = D;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("longest of ({} | {})", self.0.explain(), self.1.explain())
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        // Run both branches on clones with their own copy of the provided dest
+        let a_temp = dest.map(|dref| RefCell::new(dref.borrow().clone()));
+        let b_temp = dest.map(|dref| RefCell::new(dref.borrow().clone()));
+
+        // Internal dests are written in place, so snapshot them like `Or` does and
+        // put back whatever the branch that is not kept left behind.
+        let a_internal_backup = self.0.get_dest_mut().map(|d| d.clone());
+        let b_internal_backup = self.1.get_dest_mut().map(|d| d.clone());
+        let restore_internal = || {
+            if let Some(b) = &b_internal_backup
+                && let Some(mut d) = self.1.get_dest_mut()
+            {
+                *d = b.clone();
+            }
+            if let Some(b) = &a_internal_backup
+                && let Some(mut d) = self.0.get_dest_mut()
+            {
+                *d = b.clone();
+            }
+        };
+
+        let mut a_trial = reference.clone();
+        let mut b_trial = reference.clone();
+        let a_len = A::consume_with_dest(&self.0, &mut a_trial, a_temp.as_ref())
+            .then(|| consumed_between(reference, &a_trial));
+        let a_internal = self.0.get_dest_mut().map(|d| d.clone());
+        restore_internal();
+        let b_len = B::consume_with_dest(&self.1, &mut b_trial, b_temp.as_ref())
+            .then(|| consumed_between(reference, &b_trial));
+        let b_internal = self.1.get_dest_mut().map(|d| d.clone());
+        restore_internal();
+
+        let take_b = match (a_len, b_len) {
+            (Some(a), Some(b)) => b > a,
+            (Some(_), None) => false,
+            (None, Some(_)) => true,
+            (None, None) => return false,
+        };
+        let (trial, temp) = if take_b {
+            if let Some(s) = b_internal
+                && let Some(mut d) = self.1.get_dest_mut()
+            {
+                *d = s;
+            }
+            (b_trial, b_temp)
+        } else {
+            if let Some(s) = a_internal
+                && let Some(mut d) = self.0.get_dest_mut()
+            {
+                *d = s;
+            }
+            (a_trial, a_temp)
+        };
+
+        *reference = trial;
+        if let (Some(dref), Some(t)) = (dest, temp) {
+            *dref.borrow_mut() = t.into_inner();
+        }
+        true
+    }
+}
+
+/// A pattern that requires both sub-patterns to match the same prefix of the input.
+pub struct Zip<A, B>(pub A, pub B);
+
+impl<'a, Reference, A, B, DA, DB> Pattern<'a, Reference> for Zip<A, B>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    A: Pattern<'a, Reference, Dest = DA>,
+    B: Pattern<'a, Reference, Dest = DB>,
+    DA: Destination<Reference::Item> + Clone,
+    DB: Destination<Reference::Item> + Clone,
+    Reference::Item: Satisfies<<<A as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Satisfies<<<B as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Clone,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = (DA, DB);
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("({} & {})", self.0.explain(), self.1.explain())
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        // Route each side into its own temp dest, committing only if both agree
+        let (a_temp, b_temp) = match dest {
+            Some(dref) => {
+                let snapshot = dref.borrow().clone();
+                (
+                    Some(RefCell::new(snapshot.0)),
+                    Some(RefCell::new(snapshot.1)),
+                )
+            }
+            None => (None, None),
+        };
+
+        // Internal dests are written in place, so put them back on every failure path
+        let a_internal_backup = self.0.get_dest_mut().map(|d| d.clone());
+        let b_internal_backup = self.1.get_dest_mut().map(|d| d.clone());
+        let restore_internal = || {
+            if let Some(b) = &b_internal_backup
+                && let Some(mut d) = self.1.get_dest_mut()
+            {
+                *d = b.clone();
+            }
+            if let Some(b) = &a_internal_backup
+                && let Some(mut d) = self.0.get_dest_mut()
+            {
+                *d = b.clone();
+            }
+        };
+
+        let mut a_trial = reference.clone();
+        let mut b_trial = reference.clone();
+        if !A::consume_with_dest(&self.0, &mut a_trial, a_temp.as_ref())
+            || !B::consume_with_dest(&self.1, &mut b_trial, b_temp.as_ref())
+            || consumed_between(reference, &a_trial) != consumed_between(reference, &b_trial)
+        {
+            restore_internal();
+            return false;
+        }
+
+        *reference = a_trial;
+        if let (Some(dref), Some(a), Some(b)) = (dest, a_temp, b_temp) {
+            *dref.borrow_mut() = (a.into_inner(), b.into_inner());
+        }
+        true
+    }
+}
+
+/// A pattern that matches both sub-patterns in either order.
+/// The captured value is always `(A, B)`, regardless of which order matched.
+pub struct Unordered<A, B>(pub A, pub B);
+
+impl<'a, Reference, A, B, DA, DB> Pattern<'a, Reference> for Unordered<A, B>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    A: Pattern<'a, Reference, Dest = DA>,
+    B: Pattern<'a, Reference, Dest = DB>,
+    DA: Destination<Reference::Item> + Clone,
+    DB: Destination<Reference::Item> + Clone,
+    Reference::Item: Satisfies<<<A as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Satisfies<<<B as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Clone,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = (DA, DB);
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!(
+            "({} and {} in any order)",
+            self.0.explain(),
+            self.1.explain()
+        )
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let snapshot = dest.map(|dref| dref.borrow().clone());
+
+        for a_first in [true, false] {
+            // Each ordering starts from a fresh clone and fresh temp dests
+            let mut trial = reference.clone();
+            let (a_temp, b_temp) = match &snapshot {
+                Some((a, b)) => (Some(RefCell::new(a.clone())), Some(RefCell::new(b.clone()))),
+                None => (None, None),
+            };
+            // The branches differ in evaluation order, which clippy does not see
+            #[allow(clippy::if_same_then_else)]
+            let matched = if a_first {
+                A::consume_with_dest(&self.0, &mut trial, a_temp.as_ref())
+                    && B::consume_with_dest(&self.1, &mut trial, b_temp.as_ref())
+            } else {
+                B::consume_with_dest(&self.1, &mut trial, b_temp.as_ref())
+                    && A::consume_with_dest(&self.0, &mut trial, a_temp.as_ref())
+            };
+            if matched {
+                *reference = trial;
+                if let (Some(dref), Some(a), Some(b)) = (dest, a_temp, b_temp) {
+                    *dref.borrow_mut() = (a.into_inner(), b.into_inner());
+                }
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// A pattern that matches every sub-pattern exactly once, in any order.
+/// Captures are stored in the order of the sub-patterns, not the order they matched in.
+pub struct UnorderedSeq<P>(pub Vec<P>);
+
+impl<'a, Reference, P, D> Pattern<'a, Reference> for UnorderedSeq<P>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    P: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item> + Default,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = Vec<D>;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        let parts: Vec<String> = self.0.iter().map(|p| p.explain()).collect();
+        format!("({} in any order)", parts.join(", "))
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut captured: Vec<Option<D>> = self.0.iter().map(|_| None).collect();
+        let Some(end) = search_unordered(&self.0, reference, &mut captured) else {
+            return false;
+        };
+
+        *reference = end;
+        if let Some(dref) = dest {
+            dref.borrow_mut().extend(captured.into_iter().flatten());
+        }
+        true
+    }
+}
+
+/// Depth-first search over the orderings of `patterns` not yet captured,
+/// returning the iterator state after the first ordering that matches completely.
+fn search_unordered<'a, Reference, P, D>(
+    patterns: &'a [P],
+    reference: &Reference,
+    captured: &mut [Option<D>],
+) -> Option<Reference>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    P: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item> + Default,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    if captured.iter().all(Option::is_some) {
+        return Some(reference.clone());
+    }
+    for (i, pat) in patterns.iter().enumerate() {
+        if captured[i].is_some() {
+            continue;
+        }
+        let mut trial = reference.clone();
+        let inner = RefCell::new(D::default());
+        if P::consume_with_dest(pat, &mut trial, Some(&inner)) {
+            captured[i] = Some(inner.into_inner());
+            if let Some(end) = search_unordered(patterns, &trial, captured) {
+                return Some(end);
+            }
+            captured[i] = None;
+        }
+    }
+    None
+}
+
+/// A pattern that runs `A`, then builds the next pattern from `A`'s capture, created by
+/// `Pattern::and_then`.
+pub struct AndThen<A, B, F>(pub A, pub F, pub PhantomData<fn() -> B>);
+
+impl<'a, Reference, A, B, F, DA, DB> Pattern<'a, Reference> for AndThen<A, B, F>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    A: Pattern<'a, Reference, Dest = DA>,
+    B: for<'b> Pattern<'b, Reference, Dest = DB>,
+    F: Fn(DA) -> Option<B>,
+    DA: Destination<Reference::Item> + Default,
+    DB: Destination<Reference::Item> + Default,
+    Reference::Item: Satisfies<<<A as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+    for<'b> Reference::Item: Satisfies<<<B as Pattern<'b, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = DB;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("{} then a pattern chosen from it", self.0.explain())
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        let first = RefCell::new(DA::default());
+        if !A::consume_with_dest(&self.0, &mut trial, Some(&first)) {
+            return false;
+        }
+        let Some(next) = (self.1)(first.into_inner()) else {
+            return false;
+        };
+        let second = RefCell::new(DB::default());
+        if !next.consume_with_dest(&mut trial, Some(&second)) {
+            return false;
+        }
+        *reference = trial;
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = second.into_inner();
+        }
+        true
+    }
+}
+
+/// A pattern that behaves exactly like `P` but logs each attempt and its outcome, created
+/// by `Pattern::trace`.
+pub struct Trace<P>(pub P, pub &'static str);
+
+impl<'a, Reference, P> Pattern<'a, Reference> for Trace<P>
+where
+    Reference: Iterator + PeekableExt,
+    Reference::Item: std::fmt::Debug,
+    P: Pattern<'a, Reference>,
+    P::Dest: Destination<Reference::Item>,
+{
+    type Iter = P::Iter;
+    type Dest = P::Dest;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        self.0.get_iter()
+    }
+
+    fn explain(&self) -> String {
+        self.0.explain()
+    }
+
+    fn matches_empty(&self) -> bool {
+        self.0.matches_empty()
+    }
+
+    fn get_dest_mut(&self) -> Option<std::cell::RefMut<'_, Self::Dest>> {
+        self.0.get_dest_mut()
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        #[cfg(feature = "tracing")]
+        tracing::trace!("{} attempting at {:?}", self.1, reference.peek());
+        #[cfg(not(feature = "tracing"))]
+        eprintln!("[TRACE] {} attempting at {:?}", self.1, reference.peek());
+        let result = match dest {
+            Some(_) => self.0.consume_with_dest(reference, dest),
+            None => self.0.consume(reference),
+        };
+        #[cfg(feature = "tracing")]
+        tracing::trace!("{} -> {}", self.1, result);
+        #[cfg(not(feature = "tracing"))]
+        eprintln!("[TRACE] {} -> {}", self.1, result);
+        result
+    }
+}
+
+/// A pattern driven by a hand-written parser: `F` reads from the input and returns the
+/// capture, or `None` to fail. It runs on a copy of the input, so a failed attempt never
+/// consumes anything, however far `F` read.
+pub struct PatternFn<F, D>(pub F, pub PhantomData<fn() -> D>);
+
+impl<F, D> PatternFn<F, D> {
+    pub const fn new(f: F) -> Self {
+        PatternFn(f, PhantomData)
+    }
+}
+
+impl<'a, Reference, F, D> Pattern<'a, Reference> for PatternFn<F, D>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    F: Fn(&mut Reference) -> Option<D>,
+    D: Destination<Reference::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = D;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        "custom pattern".to_string()
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        let Some(value) = (self.0)(&mut trial) else {
+            return false;
+        };
+        *reference = trial;
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = value;
+        }
+        true
+    }
+}
+
+/// A pattern that matches the text of a `format_args!` call, usually built with
+/// `fmt_pat!`, e.g. `fmt_pat!("user_{}", id)`. The text is compared with the input while
+/// it is being formatted, so it is never collected into a `String` first.
+#[derive(Clone, Copy)]
+pub struct FormatPattern<'f>(pub std::fmt::Arguments<'f>);
+
+/// Build a `FormatPattern` from `format!`-style arguments.
+#[macro_export]
+macro_rules! fmt_pat {
+    ($($t:tt)*) => {
+        $crate::FormatPattern(format_args!($($t)*))
+    };
+}
+
+/// Feeds formatted text to the input one character at a time, failing the formatting at
+/// the first mismatch.
+struct FormatMatcher<'r, Reference> {
+    reference: &'r mut Reference,
+    captured: &'r mut String,
+}
+
+impl<Reference> std::fmt::Write for FormatMatcher<'_, Reference>
+where
+    Reference: Iterator<Item = char> + PeekableExt,
+{
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        for expected in s.chars() {
+            if self.reference.peek() != Some(&expected) {
+                return Err(std::fmt::Error);
+            }
+            self.reference.next();
+            self.captured.push(expected);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, Reference> Pattern<'a, Reference> for FormatPattern<'_>
+where
+    Reference: Iterator<Item = char> + Clone + PeekableExt,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = String;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("\"{}\"", self.0)
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        // Match on a trial so a mismatch part way through leaves everything untouched
+        let mut trial = reference.clone();
+        let mut captured = String::new();
+        let mut matcher = FormatMatcher {
+            reference: &mut trial,
+            captured: &mut captured,
+        };
+        if std::fmt::write(&mut matcher, self.0).is_err() {
+            return false;
+        }
+        *reference = trial;
+        if let Some(dref) = dest {
+            dref.borrow_mut().push_str(&captured);
+        }
+        true
+    }
+}
+
+/// A pattern that behaves exactly like `P` but calls `F` before each attempt, created by
+/// `Pattern::inspect`.
+pub struct Inspect<P, F>(pub P, pub F);
+
+impl<'a, Reference, P, F> Pattern<'a, Reference> for Inspect<P, F>
+where
+    Reference: Iterator + PeekableExt,
+    P: Pattern<'a, Reference>,
+    P::Dest: Destination<Reference::Item>,
+    F: Fn(&Reference),
+{
+    type Iter = P::Iter;
+    type Dest = P::Dest;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        self.0.get_iter()
+    }
+
+    fn explain(&self) -> String {
+        self.0.explain()
+    }
+
+    fn matches_empty(&self) -> bool {
+        self.0.matches_empty()
+    }
+
+    fn get_dest_mut(&self) -> Option<std::cell::RefMut<'_, Self::Dest>> {
+        self.0.get_dest_mut()
+    }
+
+    fn consume(&'a self, reference: &mut Reference) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        (self.1)(reference);
+        self.0.consume(reference)
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        (self.1)(reference);
+        self.0.consume_with_dest(reference, dest)
+    }
+
+    #[cfg(feature = "ambiguous")]
+    fn consume_all(
+        &'a self,
+        reference: &Reference,
+        seed: Self::Dest,
+    ) -> Vec<(Reference, Self::Dest)>
+    where
+        Reference: Clone + PeekableExt,
+        Self::Dest: Destination<Reference::Item> + Clone,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        (self.1)(reference);
+        self.0.consume_all(reference, seed)
+    }
+}
+
+/// A type that can be read from string input, usually implemented with
+/// `#[derive(Pattern)]`. On failure the reference may be left partly consumed.
+pub trait FromPattern: Sized {
+    fn consume_from(reference: &mut Checkpoint<std::iter::Peekable<Chars<'_>>>) -> Option<Self>;
+}
+
+/// A pattern that reads a `T` from string input and captures it, e.g. `Rgb::PATTERN`
+/// for a struct deriving `Pattern`. Nothing is consumed unless `T` is read in full.
+pub struct Parsed<T>(PhantomData<fn() -> T>);
+
+impl<T> Parsed<T> {
+    pub const fn new() -> Self {
+        Parsed(PhantomData)
+    }
+}
+
+impl<T> Default for Parsed<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, 's, T> Pattern<'a, Checkpoint<std::iter::Peekable<Chars<'s>>>> for Parsed<T>
+where
+    T: FromPattern + Destination<char>,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = Option<T>;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("a {}", std::any::type_name::<T>())
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Checkpoint<std::iter::Peekable<Chars<'s>>>,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool {
+        let mut trial = reference.clone();
+        let Some(value) = T::consume_from(&mut trial) else {
+            return false;
+        };
+        *reference = trial;
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = Some(value);
+        }
+        true
+    }
+}
+
+/// A zero-width assertion that succeeds between a word and a non-word character, where
+/// word characters are alphanumerics and `_`. Input start and end count as non-word.
+pub struct WordBoundary;
+
+pub const WORD_BOUNDARY: WordBoundary = WordBoundary;
+
+impl<'a, I> Pattern<'a, Checkpoint<I>> for WordBoundary
+where
+    I: Iterator<Item = char> + PeekableExt,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = ();
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        "word boundary".to_string()
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Checkpoint<I>,
+        _dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        char: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let is_word = |ch: Option<&char>| ch.is_some_and(|ch| ch.is_alphanumeric() || *ch == '_');
+        let before = is_word(reference.previous());
+        let after = is_word(reference.peek());
+        before != after
+    }
+}
+
+/// A zero-width pattern that matches only at the end of the input, written `$` in
+/// `matches!`.
+pub struct End;
+
+pub const END: End = End;
+
+impl<'a, Reference> Pattern<'a, Reference> for End
+where
+    Reference: Iterator + PeekableExt,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = ();
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        "end of input".to_string()
+    }
+
+    fn matches_empty(&self) -> bool {
+        true
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        _dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        reference.peek().is_none()
+    }
+}
+
+/// A zero-width pattern that succeeds where `P` would match, without consuming anything,
+/// written `&p` in `matches!`. Its capture is `()`, since nothing is consumed.
+pub struct Lookahead<P>(pub P);
+
+impl<'a, Reference, P> Pattern<'a, Reference> for Lookahead<P>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    P: Pattern<'a, Reference>,
+    P::Dest: Destination<Reference::Item>,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = ();
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("lookahead {}", self.0.explain())
+    }
+
+    fn matches_empty(&self) -> bool {
+        true
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        _dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        self.0.peek_match(reference)
+    }
+}
+
+/// A zero-width pattern that succeeds where `P` would not match, without consuming
+/// anything, written `!p` in `matches!`. For example `!"if", ALPHABETIC` matches any
+/// word except one starting with `if`.
+pub struct NegLookahead<P>(pub P);
+
+impl<'a, Reference, P> Pattern<'a, Reference> for NegLookahead<P>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    P: Pattern<'a, Reference>,
+    P::Dest: Destination<Reference::Item>,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = ();
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("not {}", self.0.explain())
+    }
+
+    fn matches_empty(&self) -> bool {
+        true
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        _dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        !self.0.peek_match(reference)
+    }
+}
+
+/// A pattern that consumes and captures a single item, as long as `P` would not match
+/// at it. Unlike `NegLookahead` it advances, so `Not(")")` matches any one character
+/// other than a closing paren. Fails at the end of the input.
+pub struct Not<P>(pub P);
+
+impl<'a, Reference, P> Pattern<'a, Reference> for Not<P>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    P: Pattern<'a, Reference>,
+    P::Dest: Destination<Reference::Item>,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = Reference::Item;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("any item but {}", self.0.explain())
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        if self.0.peek_match(reference) {
+            return false;
+        }
+        let Some(item) = reference.next() else {
+            return false;
+        };
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = item;
+        }
+        true
+    }
+}
+
+/// A pattern that matches a single character from a fixed set.
+pub struct AnyOf<const N: usize>(pub [char; N]);
+
+impl<'a, Reference, const N: usize> Pattern<'a, Reference> for AnyOf<N>
+where
+    Reference: Iterator<Item = char> + PeekableExt,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = char;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("any of {:?}", self.0)
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        match reference.peek() {
+            Some(ch) if self.0.contains(ch) => {
+                let ch = *ch;
+                reference.next();
+                if let Some(dref) = dest {
+                    *dref.borrow_mut() = ch;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A pattern that matches a single character outside a fixed set.
+pub struct NoneOf<const N: usize>(pub [char; N]);
+
+impl<'a, Reference, const N: usize> Pattern<'a, Reference> for NoneOf<N>
+where
+    Reference: Iterator<Item = char> + PeekableExt,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = char;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("none of {:?}", self.0)
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        match reference.peek() {
+            Some(ch) if !self.0.contains(ch) => {
+                let ch = *ch;
+                reference.next();
+                if let Some(dref) = dest {
+                    *dref.borrow_mut() = ch;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Implements `Pattern` for a set, matching a single item that is a member of it. This is
+/// the runtime counterpart of `AnyOf`, for sets built while the program runs.
+macro_rules! impl_pattern_set {
+    ($set:ident, $($bound:path),+) => {
+        impl<'a, Reference, T> Pattern<'a, Reference> for $set<T>
+        where
+            Reference: Iterator + PeekableExt,
+            Reference::Item: std::borrow::Borrow<T>,
+            T: Clone $(+ $bound)+,
+        {
+            type Iter = core::iter::Empty<Reference::Item>;
+            type Dest = T;
+
+            fn get_iter(&'a self) -> Self::Iter {
+                core::iter::empty()
+            }
+
+            fn explain(&self) -> String {
+                format!("any of {} items", self.len())
+            }
+
+            fn consume_with_dest(
+                &'a self,
+                reference: &mut Reference,
+                dest: Option<&RefCell<Self::Dest>>,
+            ) -> bool
+            where
+                Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+            {
+                match reference.peek() {
+                    Some(item) if self.contains(std::borrow::Borrow::borrow(item)) => {
+                        let item = reference.next().unwrap();
+                        if let Some(dref) = dest {
+                            *dref.borrow_mut() = std::borrow::Borrow::<T>::borrow(&item).clone();
+                        }
+                        true
+                    }
+                    _ => false,
+                }
+            }
+        }
+    };
+}
+
+impl_pattern_set!(HashSet, Eq, Hash);
+impl_pattern_set!(BTreeSet, Ord);
+
+/// Consume the next character if `predicate` accepts it, capturing it into `dest`.
+fn consume_char_if<Reference>(
+    reference: &mut Reference,
+    dest: Option<&RefCell<char>>,
+    predicate: impl Fn(&char) -> bool,
+) -> bool
+where
+    Reference: Iterator<Item = char> + PeekableExt,
+{
+    match reference.peek() {
+        Some(ch) if predicate(ch) => {
+            let ch = *ch;
+            reference.next();
+            if let Some(dref) = dest {
+                *dref.borrow_mut() = ch;
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Implements `Pattern` and `Satisfies` for a range of characters, matching a single
+/// character inside it.
+macro_rules! impl_pattern_char_range {
+    ($range:ident) => {
+        impl<'a, Reference> Pattern<'a, Reference> for $range<char>
+        where
+            Reference: Iterator<Item = char> + PeekableExt,
+        {
+            type Iter = core::iter::Empty<char>;
+            type Dest = char;
+
+            fn get_iter(&'a self) -> Self::Iter {
+                core::iter::empty()
+            }
+
+            fn explain(&self) -> String {
+                format!("any of {:?}", self)
+            }
+
+            fn consume_with_dest(
+                &'a self,
+                reference: &mut Reference,
+                dest: Option<&RefCell<Self::Dest>>,
+            ) -> bool
+            where
+                Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+            {
+                consume_char_if(reference, dest, |ch| self.contains(ch))
+            }
+        }
+
+        impl Satisfies<$range<char>> for char {
+            fn satisfies(&self, item: &$range<char>) -> bool {
+                item.contains(self)
+            }
+        }
+    };
+}
 
-            // try to consume both parts routing to temp dests
-            if A::consume_with_dest(&self.0, reference_iter, Some(&a_temp))
-                && B::consume_with_dest(&self.1, reference_iter, Some(&b_temp))
+impl_pattern_char_range!(RangeInclusive);
+impl_pattern_char_range!(Range);
+
+/// Implements `Pattern` for a character predicate function, matching a single character
+/// it accepts, e.g. `char::is_alphabetic as fn(char) -> bool`.
+macro_rules! impl_pattern_char_fn {
+    ($fn_ty:ty, |$f:ident, $ch:ident| $call:expr) => {
+        impl<'a, Reference> Pattern<'a, Reference> for $fn_ty
+        where
+            Reference: Iterator<Item = char> + PeekableExt,
+        {
+            type Iter = core::iter::Empty<char>;
+            type Dest = char;
+
+            fn get_iter(&'a self) -> Self::Iter {
+                core::iter::empty()
+            }
+
+            fn explain(&self) -> String {
+                "a character accepted by a predicate".to_string()
+            }
+
+            fn consume_with_dest(
+                &'a self,
+                reference: &mut Reference,
+                dest: Option<&RefCell<Self::Dest>>,
+            ) -> bool
+            where
+                Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
             {
-                // commit back into original dest
-                let mut d = dref.borrow_mut();
-                d.0 = a_temp.into_inner();
-                d.1 = b_temp.into_inner();
-                return true;
+                let $f = *self;
+                consume_char_if(reference, dest, |$ch| $call)
             }
-            return false;
         }
-
-        self.consume(reference_iter)
-    }
+    };
 }
 
+impl_pattern_char_fn!(fn(char) -> bool, |f, ch| f(*ch));
+impl_pattern_char_fn!(fn(&char) -> bool, |f, ch| f(ch));
+
 /// A trait for types that can collect captured items into a destination.
 pub trait Collector<Inner, Item> {
     fn commit(out: &RefCell<Self>, captured: Inner);
@@ -496,6 +3297,15 @@ where
     }
 }
 
+impl<Inner, Item> Collector<Inner, Item> for BinaryHeap<Inner>
+where
+    Inner: Destination<Item> + Default + Clone + Ord,
+{
+    fn commit(out: &RefCell<Self>, captured: Inner) {
+        out.borrow_mut().push(captured);
+    }
+}
+
 /// A pattern that captures matched items into a destination.
 pub struct To<'a, A, D>(pub A, pub &'a dest::Dest<D>);
 
@@ -513,10 +3323,30 @@ where
         self.0.get_iter()
     }
 
+    fn explain(&self) -> String {
+        format!("capture {}", self.0.explain())
+    }
+
+    fn matches_empty(&self) -> bool {
+        self.0.matches_empty()
+    }
+
     fn get_dest_mut(&self) -> Option<std::cell::RefMut<'_, Self::Dest>> {
         Some(self.1.borrow_mut())
     }
 
+    /// Reports the captured pattern without writing to the destination.
+    #[cfg(feature = "debug-report")]
+    fn consume_report(&'a self, reference: &mut Reference) -> DebugMatchReport
+    where
+        Reference: PeekableExt + Clone,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item> + Clone + std::fmt::Debug,
+    {
+        let inner = self.0.consume_report(reference);
+        DebugMatchReport::sequence(self.explain(), inner.succeeded, vec![inner])
+    }
+
     fn consume(&'a self, reference: &mut Reference) -> bool
     where
         Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
@@ -557,7 +3387,42 @@ where
     }
 }
 
+/// Match each child in order, appending their captures to `dest` only if all succeed.
+fn consume_seq<'a, Reference, A, D>(
+    children: &'a [A],
+    reference: &mut Reference,
+    dest: Option<&RefCell<Vec<D>>>,
+) -> bool
+where
+    Reference: Iterator + Clone + PeekableExt,
+    A: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item> + Default,
+    Reference::Item: Satisfies<<<A as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    let mut trial = reference.clone();
+
+    let mut temp: Vec<D> = Vec::new();
+
+    for child in children {
+        let inner = RefCell::new(D::default());
+        if !A::consume_with_dest(child, &mut trial, Some(&inner)) {
+            return false;
+        }
+        temp.push(inner.into_inner());
+    }
+
+    *reference = trial;
+
+    if let Some(dref) = dest {
+        let mut d = dref.borrow_mut();
+        d.extend(temp);
+    }
+
+    true
+}
+
 /// A pattern that matches a sequence of sub-patterns.
+/// If any sub-pattern fails, the reference is left where it was and nothing is captured.
 pub struct Seq<A, const N: usize>(pub [A; N]);
 
 impl<'a, Reference, A, D, const N: usize> Pattern<'a, Reference> for Seq<A, N>
@@ -574,6 +3439,11 @@ where
         core::iter::empty().peekable()
     }
 
+    fn explain(&self) -> String {
+        let parts: Vec<String> = self.0.iter().map(|p| p.explain()).collect();
+        parts.join(" then ")
+    }
+
     fn consume_with_dest(
         &'a self,
         reference: &mut Reference,
@@ -582,26 +3452,39 @@ where
     where
         Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
     {
-        let mut trial = reference.clone();
-
-        let mut temp: Vec<D> = Vec::new();
+        consume_seq(&self.0, reference, dest)
+    }
+}
 
-        for child in &self.0 {
-            let inner = RefCell::new(D::default());
-            if !A::consume_with_dest(child, &mut trial, Some(&inner)) {
-                return false;
-            }
-            temp.push(inner.into_inner());
-        }
+/// An array of patterns matches each of them in order, like `Seq`.
+impl<'a, Reference, A, D, const N: usize> Pattern<'a, Reference> for [A; N]
+where
+    Reference: Iterator + Clone + PeekableExt,
+    A: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item> + Default,
+    Reference::Item: Satisfies<<<A as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = Vec<D>;
 
-        *reference = trial;
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
 
-        if let Some(dref) = dest {
-            let mut d = dref.borrow_mut();
-            d.extend(temp);
-        }
+    fn explain(&self) -> String {
+        let parts: Vec<String> = self.iter().map(|p| p.explain()).collect();
+        parts.join(" then ")
+    }
 
-        true
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        consume_seq(self, reference, dest)
     }
 }
 
@@ -632,6 +3515,28 @@ where
         core::iter::empty()
     }
 
+    fn explain(&self) -> String {
+        format!("({})*", self.end.explain())
+    }
+
+    fn matches_empty(&self) -> bool {
+        true
+    }
+
+    #[cfg(feature = "ambiguous")]
+    fn consume_all(
+        &'a self,
+        reference: &Reference,
+        seed: Self::Dest,
+    ) -> Vec<(Reference, Self::Dest)>
+    where
+        Reference: Clone + PeekableExt,
+        Self::Dest: Destination<Reference::Item> + Clone,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        consume_all_repeat(&self.end, 0, reference, seed)
+    }
+
     fn consume_with_dest(
         &'a self,
         reference: &mut Reference,
@@ -698,6 +3603,28 @@ where
         core::iter::empty()
     }
 
+    fn explain(&self) -> String {
+        format!("({})+", self.end.explain())
+    }
+
+    fn matches_empty(&self) -> bool {
+        self.end.matches_empty()
+    }
+
+    #[cfg(feature = "ambiguous")]
+    fn consume_all(
+        &'a self,
+        reference: &Reference,
+        seed: Self::Dest,
+    ) -> Vec<(Reference, Self::Dest)>
+    where
+        Reference: Clone + PeekableExt,
+        Self::Dest: Destination<Reference::Item> + Clone,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        consume_all_repeat(&self.end, 1, reference, seed)
+    }
+
     fn consume_with_dest(
         &'a self,
         reference: &mut Reference,
@@ -721,6 +3648,8 @@ where
                     let rem_trial: Vec<Reference::Item> = trial.clone().collect();
                     let consumed = rem_orig.len().saturating_sub(rem_trial.len());
                     if consumed == 0 {
+                        // An empty match still counts as the one required repetition
+                        any = true;
                         break;
                     }
                     for _ in 0..consumed {
@@ -742,6 +3671,7 @@ where
                     let rem_trial: Vec<Reference::Item> = trial.clone().collect();
                     let consumed = rem_orig.len().saturating_sub(rem_trial.len());
                     if consumed == 0 {
+                        any = true;
                         break;
                     }
                     for _ in 0..consumed {
@@ -749,16 +3679,476 @@ where
                     }
                     any = true;
                 }
-                any
+                any
+            }
+        }
+    }
+}
+
+/// `..` matches the rest of the input, including none of it, and captures every item
+/// it consumes. `("prefix:", ..)` therefore reads as "starts with `prefix:`".
+impl<'a, Reference> Pattern<'a, Reference> for RangeFull
+where
+    Reference: Iterator + PeekableExt,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = Vec<Reference::Item>;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        "the rest of the input".to_string()
+    }
+
+    fn matches_empty(&self) -> bool {
+        true
+    }
+
+    fn consume(&'a self, reference: &mut Reference) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        reference.for_each(drop);
+        true
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        match dest {
+            Some(dref) => dref.borrow_mut().extend(reference),
+            None => reference.for_each(drop),
+        }
+        true
+    }
+}
+
+/// Greedily match `pattern` up to `max` times, succeeding only if it matched at least
+/// `min` times. On failure nothing is consumed and nothing is captured.
+fn consume_repeat<'a, Reference, P, D>(
+    pattern: &'a P,
+    min: usize,
+    max: usize,
+    reference: &mut Reference,
+    dest: Option<&RefCell<Vec<D>>>,
+) -> bool
+where
+    Reference: Iterator + Clone + PeekableExt,
+    P: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item> + Default,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    let mut trial = reference.clone();
+    let mut captured = Vec::new();
+    while captured.len() < max {
+        let mut step = trial.clone();
+        let inner_dest = RefCell::new(D::default());
+        if !P::consume_with_dest(pattern, &mut step, Some(&inner_dest)) {
+            break;
+        }
+        // An empty match still counts, like in `..=p`. It would match the same way
+        // again, so keep counting only until `min` is reached
+        let empty = consumed_between(&trial, &step) == 0;
+        trial = step;
+        captured.push(inner_dest.into_inner());
+        if empty && captured.len() >= min {
+            break;
+        }
+    }
+    if captured.len() < min {
+        return false;
+    }
+    *reference = trial;
+    if let Some(dref) = dest {
+        dref.borrow_mut().extend(captured);
+    }
+    true
+}
+
+/// Describe why `pattern` could not be repeated `min` times: where the repetitions stopped,
+/// and how many there were.
+fn repeat_error<'a, Reference, P>(
+    pattern: &'a P,
+    min: usize,
+    reference: &Reference,
+    context: String,
+) -> PatternError<Reference::Item>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    Reference::Item: Clone,
+    P: Pattern<'a, Reference>,
+    P::Dest: Destination<Reference::Item>,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    let mut trial = reference.clone();
+    let mut got = 0;
+    while got < min {
+        let mut step = trial.clone();
+        if !pattern.consume(&mut step) || consumed_between(&trial, &step) == 0 {
+            break;
+        }
+        trial = step;
+        got += 1;
+    }
+    PatternError {
+        kind: ErrorKind::MinOccurrences { expected: min, got },
+        context: vec![context],
+        position: consumed_between(reference, &trial),
+    }
+}
+
+/// A pattern that matches a sub-pattern at least `n` times, with `n` chosen at runtime.
+pub struct RepeatMin<P>(pub P, pub usize);
+
+/// A pattern that matches a sub-pattern at most `n` times, with `n` chosen at runtime.
+pub struct RepeatMax<P>(pub P, pub usize);
+
+/// A pattern that matches a sub-pattern exactly `n` times, with `n` chosen at runtime.
+pub struct RepeatExact<P>(pub P, pub usize);
+
+macro_rules! impl_pattern_repeat {
+    ($name:ident, $n:ident => ($min:expr, $max:expr), $fmt:literal) => {
+        impl<'a, Reference, P, D> Pattern<'a, Reference> for $name<P>
+        where
+            Reference: Iterator + Clone + PeekableExt,
+            P: Pattern<'a, Reference, Dest = D>,
+            D: Destination<Reference::Item> + Default,
+            Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+        {
+            type Iter = core::iter::Empty<Reference::Item>;
+            type Dest = Vec<D>;
+
+            fn get_iter(&'a self) -> Self::Iter {
+                core::iter::empty()
+            }
+
+            fn explain(&self) -> String {
+                format!($fmt, self.0.explain(), self.1)
+            }
+
+            fn try_consume_err(
+                &'a self,
+                reference: &mut Reference,
+            ) -> Result<(), PatternError<Reference::Item>>
+            where
+                Reference: PeekableExt + Clone,
+                Self::Dest: Destination<Reference::Item>,
+                Reference::Item: Satisfies<<Self::Iter as Iterator>::Item> + Clone,
+            {
+                if self.consume(reference) {
+                    return Ok(());
+                }
+                // `RepeatMax` has no minimum, so it never gets here
+                #[allow(unused_variables)]
+                let $n = self.1;
+                Err(repeat_error(&self.0, $min, reference, self.explain()))
+            }
+
+            fn consume_with_dest(
+                &'a self,
+                reference: &mut Reference,
+                dest: Option<&RefCell<Self::Dest>>,
+            ) -> bool
+            where
+                Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+            {
+                let $n = self.1;
+                consume_repeat(&self.0, $min, $max, reference, dest)
+            }
+        }
+    };
+}
+
+impl_pattern_repeat!(RepeatMin, n => (n, usize::MAX), "({}){{{},}}");
+impl_pattern_repeat!(RepeatMax, n => (0, n), "({}){{,{}}}");
+impl_pattern_repeat!(RepeatExact, n => (n, n), "({}){{{}}}");
+
+/// A pattern that matches `P` between `min` and `max` times, inclusive, as written
+/// `p{min..=max}` or `p{min..end}` in `matches!`.
+pub struct RepeatBetween<P>(pub P, pub RangeInclusive<usize>);
+
+impl<'a, Reference, P, D> Pattern<'a, Reference> for RepeatBetween<P>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    P: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item> + Default,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = Vec<D>;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!(
+            "({}){{{},{}}}",
+            self.0.explain(),
+            self.1.start(),
+            self.1.end()
+        )
+    }
+
+    fn matches_empty(&self) -> bool {
+        *self.1.start() == 0 || self.0.matches_empty()
+    }
+
+    fn try_consume_err(
+        &'a self,
+        reference: &mut Reference,
+    ) -> Result<(), PatternError<Reference::Item>>
+    where
+        Reference: PeekableExt + Clone,
+        Self::Dest: Destination<Reference::Item>,
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item> + Clone,
+    {
+        if self.consume(reference) {
+            return Ok(());
+        }
+        Err(repeat_error(
+            &self.0,
+            *self.1.start(),
+            reference,
+            self.explain(),
+        ))
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        consume_repeat(&self.0, *self.1.start(), *self.1.end(), reference, dest)
+    }
+}
+
+/// A pattern that matches `P` repeatedly until `Term` would match, created by
+/// `Pattern::repeat_until`. `Term` is checked before every repetition but not consumed.
+/// Fails without consuming anything if `P` stops matching before `Term` is reached.
+pub struct RepeatUntil<P, Term>(pub P, pub Term);
+
+impl<'a, Reference, P, Term, D> Pattern<'a, Reference> for RepeatUntil<P, Term>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    P: Pattern<'a, Reference, Dest = D>,
+    Term: Pattern<'a, Reference>,
+    D: Destination<Reference::Item> + Default,
+    Term::Dest: Destination<Reference::Item>,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Satisfies<<<Term as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = Vec<D>;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("({}) until {}", self.0.explain(), self.1.explain())
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        let mut captured = Vec::new();
+        while !self.1.consume(&mut trial.clone()) {
+            let mut step = trial.clone();
+            let inner_dest = RefCell::new(D::default());
+            if !P::consume_with_dest(&self.0, &mut step, Some(&inner_dest))
+                || consumed_between(&trial, &step) == 0
+            {
+                return false;
+            }
+            trial = step;
+            captured.push(inner_dest.into_inner());
+        }
+        *reference = trial;
+        if let Some(dref) = dest {
+            dref.borrow_mut().extend(captured);
+        }
+        true
+    }
+}
+
+/// A lazy `..`/`..=` repetition: it matches as few repetitions as it can, trying one more
+/// each time whatever follows it in a pair or tuple fails. On its own, with nothing after
+/// it, it matches just the minimum.
+pub struct Lazy<P>(pub P);
+
+/// Match `pattern` `min` times, then once more for each failed attempt of
+/// `continuation`, stopping once `continuation` matches. On failure nothing is consumed
+/// and nothing is captured.
+fn consume_lazy<'a, 'c, Reference, P, D, C>(
+    pattern: &'a P,
+    min: usize,
+    reference: &mut Reference,
+    dest: Option<&RefCell<Vec<D>>>,
+    continuation: &'c C,
+    continuation_dest: Option<&RefCell<C::Dest>>,
+) -> bool
+where
+    Reference: Iterator + Clone + PeekableExt,
+    P: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item> + Default,
+    C: Pattern<'c, Reference>,
+    C::Dest: Destination<Reference::Item> + Clone,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Satisfies<<C::Iter as Iterator>::Item>,
+{
+    let mut trial = reference.clone();
+    let mut captured = Vec::new();
+    loop {
+        if captured.len() >= min {
+            let mut rest = trial.clone();
+            let temp = continuation_dest.map(|d| RefCell::new(d.borrow().clone()));
+            let matched = match &temp {
+                Some(t) => continuation.consume_with_dest(&mut rest, Some(t)),
+                None => continuation.consume(&mut rest),
+            };
+            if matched {
+                if let (Some(d), Some(t)) = (continuation_dest, temp) {
+                    *d.borrow_mut() = t.into_inner();
+                }
+                trial = rest;
+                break;
+            }
+        }
+        let mut step = trial.clone();
+        let inner_dest = RefCell::new(D::default());
+        if !P::consume_with_dest(pattern, &mut step, Some(&inner_dest))
+            || consumed_between(&trial, &step) == 0
+        {
+            return false;
+        }
+        trial = step;
+        captured.push(inner_dest.into_inner());
+    }
+    *reference = trial;
+    if let Some(dref) = dest {
+        dref.borrow_mut().extend(captured);
+    }
+    true
+}
+
+/// Implements `Pattern` for `Lazy` over a range repetition with the given minimum count.
+macro_rules! impl_pattern_lazy {
+    ($range:ident, $min:literal, $fmt:literal) => {
+        impl<'a, Reference, P, D> Pattern<'a, Reference> for Lazy<$range<P>>
+        where
+            Reference: Iterator + Clone + PeekableExt,
+            P: Pattern<'a, Reference, Dest = D>,
+            D: Destination<Reference::Item> + Default,
+            Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+        {
+            type Iter = core::iter::Empty<Reference::Item>;
+            type Dest = Vec<D>;
+
+            fn get_iter(&'a self) -> Self::Iter {
+                core::iter::empty()
+            }
+
+            fn explain(&self) -> String {
+                format!($fmt, self.0.end.explain())
+            }
+
+            fn consume_with_dest(
+                &'a self,
+                reference: &mut Reference,
+                dest: Option<&RefCell<Self::Dest>>,
+            ) -> bool
+            where
+                Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+            {
+                consume_repeat(&self.0.end, $min, $min, reference, dest)
+            }
+
+            fn consume_with_continuation<'c, C>(
+                &'a self,
+                reference: &mut Reference,
+                dest: Option<&RefCell<Self::Dest>>,
+                continuation: &'c C,
+                continuation_dest: Option<&RefCell<C::Dest>>,
+            ) -> bool
+            where
+                Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+                C: Pattern<'c, Reference>,
+                C::Dest: Destination<Reference::Item> + Clone,
+                Reference::Item: Satisfies<<C::Iter as Iterator>::Item>,
+            {
+                consume_lazy(
+                    &self.0.end,
+                    $min,
+                    reference,
+                    dest,
+                    continuation,
+                    continuation_dest,
+                )
+            }
+        }
+    };
+}
+
+impl_pattern_lazy!(RangeTo, 0, "({})*?");
+impl_pattern_lazy!(RangeToInclusive, 1, "({})+?");
+
+/// Every way of matching `pattern` at least `min` times in a row, appending each
+/// repetition's capture to `seed`. Repetitions that consume nothing are not followed.
+#[cfg(feature = "ambiguous")]
+fn consume_all_repeat<'a, Reference, P, D>(
+    pattern: &'a P,
+    min: usize,
+    reference: &Reference,
+    seed: Vec<D>,
+) -> Vec<(Reference, Vec<D>)>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    P: Pattern<'a, Reference, Dest = D>,
+    D: Destination<Reference::Item> + Default + Clone,
+    Reference::Item: Satisfies<<<P as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    let mut outcomes = Vec::new();
+    let mut frontier = vec![(reference.clone(), seed, 0usize)];
+    while let Some((iter, dest, count)) = frontier.pop() {
+        for (rest, d) in pattern.consume_all(&iter, D::default()) {
+            if consumed_between(&iter, &rest) == 0 {
+                continue;
             }
+            let mut next = dest.clone();
+            next.push(d);
+            frontier.push((rest, next, count + 1));
+        }
+        if count >= min {
+            outcomes.push((iter, dest));
         }
     }
+    outcomes
 }
 
 /// A pattern that matches a sequence of sub-patterns separated by a separator pattern.
 pub struct Sep<Sep, P>(pub Sep, pub P);
 
-/// A pattern that matches one or more occurrences of a sub-pattern separated by a separator pattern.
+/// A pattern that matches one or more occurrences of a sub-pattern with a separator
+/// between each pair, e.g. `1,2,3`. Leading and trailing separators are not consumed.
+/// Each capture pairs an element with the separator before it; the first element's
+/// separator is `Default::default()`.
 pub struct Sep1<Sep, P>(pub Sep, pub P);
 
 impl<'a, Reference, SepT, PatT, SD, PD> Pattern<'a, Reference> for Sep<SepT, PatT>
@@ -779,6 +4169,14 @@ where
         core::iter::empty()
     }
 
+    fn explain(&self) -> String {
+        format!("({}) separated by ({})", self.1.explain(), self.0.explain())
+    }
+
+    fn matches_empty(&self) -> bool {
+        true
+    }
+
     fn consume_with_dest(
         &'a self,
         reference: &mut Reference,
@@ -847,6 +4245,18 @@ where
         core::iter::empty()
     }
 
+    fn explain(&self) -> String {
+        format!(
+            "({}) separated by ({}), at least once",
+            self.1.explain(),
+            self.0.explain()
+        )
+    }
+
+    fn matches_empty(&self) -> bool {
+        self.1.matches_empty()
+    }
+
     fn consume_with_dest(
         &'a self,
         reference: &mut Reference,
@@ -858,30 +4268,18 @@ where
         // Require at least one occurrence
         let mut temp: Vec<(SD, PD)> = Vec::new();
 
-        // First element must be a pattern
+        // First element must be a pattern; it has no separator before it, so its
+        // separator slot keeps the default
         let mut trial = reference.clone();
         let first_pat = RefCell::new(PD::default());
         if !PatT::consume_with_dest(&self.1, &mut trial, Some(&first_pat)) {
             return false;
         }
+        *reference = trial;
+        temp.push((SD::default(), first_pat.into_inner()));
 
-        // Try optional separator after first element
-        let first_sep = RefCell::new(SD::default());
-        let mut trial_after_sep = trial.clone();
-        if SepT::consume_with_dest(&self.0, &mut trial_after_sep, Some(&first_sep)) {
-            trial = trial_after_sep;
-        }
-
-        // advance real iterator and record
-        let rem_orig: Vec<Reference::Item> = reference.clone().collect();
-        let rem_trial: Vec<Reference::Item> = trial.clone().collect();
-        let consumed = rem_orig.len().saturating_sub(rem_trial.len());
-        for _ in 0..consumed {
-            reference.next();
-        }
-        temp.push((first_sep.into_inner(), first_pat.into_inner()));
-
-        // subsequent (sep, pat)*
+        // subsequent (sep, pat)*; a separator is only consumed along with the element
+        // after it, so no trailing separator is taken
         loop {
             let mut trial = reference.clone();
 
@@ -916,3 +4314,530 @@ where
         true
     }
 }
+
+/// A pattern that matches one or more elements, each followed by a mandatory separator,
+/// e.g. `a; b;`. Only the element captures are kept.
+pub struct SepTerminated<Sep, P>(pub Sep, pub P);
+
+impl<'a, Reference, SepT, PatT, SD, PD> Pattern<'a, Reference> for SepTerminated<SepT, PatT>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    SepT: Pattern<'a, Reference, Dest = SD>,
+    PatT: Pattern<'a, Reference, Dest = PD>,
+    SD: Destination<Reference::Item> + Default,
+    PD: Destination<Reference::Item> + Default,
+    Reference::Item: Satisfies<<<SepT as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Satisfies<<<PatT as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = Vec<PD>;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!(
+            "({}) each terminated by ({}), at least once",
+            self.1.explain(),
+            self.0.explain()
+        )
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut temp: Vec<PD> = Vec::new();
+
+        loop {
+            let mut trial = reference.clone();
+
+            // element then its separator; both are required
+            let pat_temp = RefCell::new(PD::default());
+            if !PatT::consume_with_dest(&self.1, &mut trial, Some(&pat_temp)) {
+                break;
+            }
+            let sep_temp = RefCell::new(SD::default());
+            if !SepT::consume_with_dest(&self.0, &mut trial, Some(&sep_temp)) {
+                break;
+            }
+            if consumed_between(reference, &trial) == 0 {
+                break;
+            }
+
+            *reference = trial;
+            temp.push(pat_temp.into_inner());
+        }
+
+        if temp.is_empty() {
+            return false;
+        }
+
+        if let Some(dref) = dest {
+            let mut d = dref.borrow_mut();
+            d.extend(temp);
+        }
+
+        true
+    }
+}
+
+/// A pattern that matches exactly `N` elements separated by a separator, i.e.
+/// `P (S P){N-1}`, as in dotted IPv4 addresses. `SepN<_, _, 0>` matches nothing
+/// and `SepN<_, _, 1>` is equivalent to `P`. Only the element captures are kept,
+/// and nothing is consumed unless all `N` elements match.
+pub struct SepN<Sep, P, const N: usize>(pub Sep, pub P);
+
+impl<'a, Reference, SepT, PatT, SD, PD, const N: usize> Pattern<'a, Reference>
+    for SepN<SepT, PatT, N>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    SepT: Pattern<'a, Reference, Dest = SD>,
+    PatT: Pattern<'a, Reference, Dest = PD>,
+    SD: Destination<Reference::Item> + Default,
+    PD: Destination<Reference::Item> + Default,
+    Reference::Item: Satisfies<<<SepT as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Satisfies<<<PatT as Pattern<'a, Reference>>::Iter as Iterator>::Item>,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = Vec<PD>;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!(
+            "({}) separated by ({}), exactly {} times",
+            self.1.explain(),
+            self.0.explain(),
+            N
+        )
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        let mut temp: Vec<PD> = Vec::with_capacity(N);
+
+        for i in 0..N {
+            if i > 0 {
+                let sep_temp = RefCell::new(SD::default());
+                if !SepT::consume_with_dest(&self.0, &mut trial, Some(&sep_temp)) {
+                    return false;
+                }
+            }
+            let pat_temp = RefCell::new(PD::default());
+            if !PatT::consume_with_dest(&self.1, &mut trial, Some(&pat_temp)) {
+                return false;
+            }
+            temp.push(pat_temp.into_inner());
+        }
+
+        *reference = trial;
+
+        if let Some(dref) = dest {
+            let mut d = dref.borrow_mut();
+            d.extend(temp);
+        }
+
+        true
+    }
+}
+
+/// A pattern like `Sep` that routes separator and element captures into separate destinations.
+/// When a caller passes its own dest, the captures go there as `(separators, elements)`
+/// instead.
+pub struct SepWith<'a, SepT, PatT, SD, PD>(
+    pub SepT,
+    pub PatT,
+    pub &'a dest::Dest<Vec<SD>>,
+    pub &'a dest::Dest<Vec<PD>>,
+);
+
+impl<'a, Reference, SepT, PatT, SD, PD> Pattern<'a, Reference> for SepWith<'a, SepT, PatT, SD, PD>
+where
+    Reference: Iterator + Clone + PeekableExt,
+    SepT: Pattern<'a, Reference, Dest = SD>,
+    PatT: Pattern<'a, Reference, Dest = PD>,
+    SD: Destination<Reference::Item> + Default + Clone,
+    PD: Destination<Reference::Item> + Default + Clone,
+    Reference::Item: Satisfies<<<SepT as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Satisfies<<<PatT as Pattern<'a, Reference>>::Iter as Iterator>::Item>
+        + Clone,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = (Vec<SD>, Vec<PD>);
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("({}) separated by ({})", self.1.explain(), self.0.explain())
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut seps: Vec<SD> = Vec::new();
+        let mut elems: Vec<PD> = Vec::new();
+
+        loop {
+            let mut trial = reference.clone();
+            let pat_dest = RefCell::new(PD::default());
+            if !PatT::consume_with_dest(&self.1, &mut trial, Some(&pat_dest)) {
+                break;
+            }
+
+            // Only separators that are actually present are recorded
+            let sep_dest = RefCell::new(SD::default());
+            let mut trial_after_sep = trial.clone();
+            let sep_matched =
+                SepT::consume_with_dest(&self.0, &mut trial_after_sep, Some(&sep_dest));
+            if sep_matched {
+                trial = trial_after_sep;
+            }
+
+            if consumed_between(reference, &trial) == 0 {
+                break;
+            }
+            *reference = trial;
+
+            elems.push(pat_dest.into_inner());
+            if sep_matched {
+                seps.push(sep_dest.into_inner());
+            }
+        }
+
+        // A caller that provides a dest owns the capture, and may still discard it if
+        // an enclosing pattern fails, so the side dests are only written without one.
+        if let Some(dref) = dest {
+            let mut d = dref.borrow_mut();
+            d.0.extend(seps);
+            d.1.extend(elems);
+        } else {
+            self.2.borrow_mut().extend(seps);
+            self.3.borrow_mut().extend(elems);
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exts::{ALPHABETIC, NUM, WS};
+
+    const ANY: fn(char) -> bool = |_| true;
+
+    /// Generate a capture test and a rollback test for the tuple of the given literals.
+    /// The rollback input is the concatenated literals with the last one replaced by `#`.
+    macro_rules! tuple_tests {
+        ($capture:ident, $rollback:ident, $($lit:literal),+) => {
+            #[test]
+            fn $capture() {
+                let pattern = ($($lit,)+);
+                let mut reference = concat!($($lit),+).chars().peekable();
+                let dest = RefCell::new(Default::default());
+                assert!(pattern.consume_with_dest(&mut reference, Some(&dest)));
+                assert_eq!(reference.next(), None);
+                assert_eq!(dest.into_inner(), ($(String::from($lit),)+));
+            }
+
+            #[test]
+            fn $rollback() {
+                let pattern = ($($lit,)+);
+                let full = concat!($($lit),+);
+                let input = format!("{}#", &full[..full.len() - 1]);
+                let mut reference = input.chars().peekable();
+                let seeded = ($(String::from(concat!("old ", $lit)),)+);
+                let dest = RefCell::new(seeded.clone());
+                assert!(!pattern.consume_with_dest(&mut reference, Some(&dest)));
+                assert_eq!(reference.collect::<String>(), input);
+                assert_eq!(dest.into_inner(), seeded);
+            }
+        };
+    }
+
+    tuple_tests!(tuple3_captures, tuple3_rolls_back, "a", "b", "c");
+    tuple_tests!(tuple4_captures, tuple4_rolls_back, "a", "b", "c", "d");
+    tuple_tests!(tuple5_captures, tuple5_rolls_back, "a", "b", "c", "d", "e");
+    tuple_tests!(
+        tuple6_captures,
+        tuple6_rolls_back,
+        "a",
+        "b",
+        "c",
+        "d",
+        "e",
+        "f"
+    );
+    tuple_tests!(
+        tuple7_captures,
+        tuple7_rolls_back,
+        "a",
+        "b",
+        "c",
+        "d",
+        "e",
+        "f",
+        "g"
+    );
+    tuple_tests!(
+        tuple8_captures,
+        tuple8_rolls_back,
+        "a",
+        "b",
+        "c",
+        "d",
+        "e",
+        "f",
+        "g",
+        "h"
+    );
+
+    #[test]
+    fn rollback_restores_position() {
+        let mut checkpoint = Checkpoint::new("abcdefghij".chars().peekable());
+        checkpoint.by_ref().take(5).for_each(drop);
+        checkpoint.begin();
+        checkpoint.by_ref().take(3).for_each(drop);
+        assert_eq!(checkpoint.position(), 8);
+        checkpoint.rollback();
+        assert_eq!(checkpoint.position(), 5);
+        assert_eq!(checkpoint.next(), Some('f'));
+    }
+
+    #[test]
+    fn or_restores_shared_dest_after_failed_branch() {
+        let d: dest::Dest<Vec<String>> = dest::Dest::new();
+        let pattern = Or(Followed(To("a", &d), "x"), To("b", &d));
+        assert!(!pattern.matches(&"az"));
+        assert_eq!(d, Vec::<String>::new());
+    }
+
+    #[test]
+    fn seq_leaves_reference_and_dest_on_mid_sequence_failure() {
+        let pattern = Seq(["ab", "cd", "ef"]);
+        let mut reference = "abcdxy".chars().peekable();
+        let dest = RefCell::new(vec![String::from("kept")]);
+        assert!(!pattern.consume_with_dest(&mut reference, Some(&dest)));
+        assert_eq!(reference.collect::<String>(), "abcdxy");
+        assert_eq!(dest.into_inner(), ["kept"]);
+
+        let mut reference = "abcdxy".chars().peekable();
+        assert!(!pattern.consume(&mut reference));
+        assert_eq!(reference.collect::<String>(), "abcdxy");
+
+        let mut reference = "abcdef".chars().peekable();
+        let dest = RefCell::new(Vec::new());
+        assert!(pattern.consume_with_dest(&mut reference, Some(&dest)));
+        assert_eq!(reference.next(), None);
+        assert_eq!(dest.into_inner(), ["ab", "cd", "ef"]);
+    }
+
+    #[test]
+    fn sep1_needs_a_separator_between_elements() {
+        let pattern = Sep1(",", NUM);
+        assert!(pattern.matches(&"12,34"));
+        assert!(!pattern.matches(&"12 34"));
+        assert_eq!(pattern.matches_prefix(&"12 34"), Some(2));
+
+        let mut reference = "12,34".chars().peekable();
+        let dest = RefCell::new(Vec::new());
+        assert!(pattern.consume_with_dest(&mut reference, Some(&dest)));
+        let numbers: Vec<usize> = dest.into_inner().into_iter().map(|(_, n)| n).collect();
+        assert_eq!(numbers, [12, 34]);
+    }
+
+    #[test]
+    fn sep1_leaves_a_trailing_separator() {
+        let pattern = Sep1(",", NUM);
+        assert!(!pattern.matches(&"12,34,"));
+        assert_eq!(pattern.matches_prefix(&"12,34,"), Some(5));
+    }
+
+    #[test]
+    fn single_element_tuple_delegates_to_its_element() {
+        assert!(crate::matches!("hello" => (ALPHABETIC)+));
+        assert!(!crate::matches!("hello1" => (ALPHABETIC)+));
+
+        let d: dest::Dest<String> = dest::Dest::new();
+        assert!(To((ALPHABETIC,), &d).matches(&"hello"));
+        assert_eq!(d, String::from("hello"));
+    }
+
+    #[test]
+    fn cow_str_is_iterable_when_borrowed_or_owned() {
+        let borrowed: Cow<'_, str> = Cow::Borrowed("12,34");
+        let owned: Cow<'_, str> = Cow::Owned(String::from("12,34"));
+        for input in [borrowed, owned] {
+            assert!(Sep1(",", NUM).matches(&input));
+            assert!(!Pattern::matches(&"12", &input));
+            assert_eq!(Pattern::matches_prefix(&"12", &input), Some(2));
+        }
+    }
+
+    #[test]
+    fn zero_to_one_repetition_is_optional() {
+        for input in ["", "x", "xx", "y"] {
+            assert_eq!(
+                crate::matches!(input => "x"{0,1}),
+                crate::matches!(input => "x"?),
+                "{input:?}"
+            );
+            assert_eq!(
+                RepeatBetween("x", 0..=1).matches_prefix(&input),
+                Opt("x").matches_prefix(&input),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn repeat_between_rolls_back_below_min() {
+        let pattern = RepeatBetween("ab", 2..=3);
+        let mut reference = "abx".chars().peekable();
+        let dest = RefCell::new(vec![String::from("kept")]);
+        assert!(!pattern.consume_with_dest(&mut reference, Some(&dest)));
+        assert_eq!(reference.collect::<String>(), "abx");
+        assert_eq!(dest.into_inner(), ["kept"]);
+
+        let mut reference = "abababab".chars().peekable();
+        let dest = RefCell::new(Vec::new());
+        assert!(pattern.consume_with_dest(&mut reference, Some(&dest)));
+        assert_eq!(reference.collect::<String>(), "ab");
+        assert_eq!(dest.into_inner(), ["ab", "ab", "ab"]);
+    }
+
+    #[test]
+    fn lookahead_keeps_position() {
+        let pattern = Lookahead("ab");
+        let mut reference = Checkpoint::new("abc".chars().peekable());
+        assert!(pattern.consume(&mut reference));
+        assert_eq!(reference.position(), 0);
+        assert_eq!(reference.next(), Some('a'));
+
+        let mut reference = Checkpoint::new("axc".chars().peekable());
+        assert!(!pattern.consume(&mut reference));
+        assert_eq!(reference.position(), 0);
+        assert_eq!(reference.next(), Some('a'));
+
+        assert!(crate::matches!("ab" => &"a", "ab"));
+        assert!(!crate::matches!("ab" => &"b", "ab"));
+    }
+
+    #[test]
+    fn double_negative_lookahead_is_lookahead() {
+        for input in ["ab", "ax", "b", ""] {
+            let mut negated = Checkpoint::new(input.chars().peekable());
+            let mut positive = Checkpoint::new(input.chars().peekable());
+            assert_eq!(
+                NegLookahead(NegLookahead("ab")).consume(&mut negated),
+                Lookahead("ab").consume(&mut positive),
+                "{input:?}"
+            );
+            assert_eq!(negated.position(), positive.position());
+            assert_eq!(
+                crate::matches!(input => !!"a", ALPHABETIC),
+                crate::matches!(input => &"a", ALPHABETIC),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn negative_lookahead_excludes_a_prefix() {
+        assert!(crate::matches!("else" => !"if", ALPHABETIC));
+        assert!(crate::matches!("i" => !"if", ALPHABETIC));
+        assert!(!crate::matches!("if" => !"if", ALPHABETIC));
+        assert!(!crate::matches!("iffy" => !"if", ALPHABETIC));
+    }
+
+    #[test]
+    fn lazy_retries_until_the_rest_of_a_pair_matches() {
+        assert!((Lazy(..ANY), ("b", "c")).matches(&"aabc"));
+        assert!((Lazy(..=ANY), ("b", "c")).matches(&"aabc"));
+        assert!((Lazy(..ANY), ("b", "c")).matches(&"bc"));
+        assert!(!(Lazy(..=ANY), ("b", "c")).matches(&"bc"));
+
+        let pattern = (Lazy(..ANY), ("b", "c"));
+        let mut reference = "aabcbc".chars().peekable();
+        let dest = RefCell::new(Default::default());
+        assert!(pattern.consume_with_dest(&mut reference, Some(&dest)));
+        assert_eq!(reference.collect::<String>(), "bc");
+        let (repeated, rest) = dest.into_inner();
+        assert_eq!(repeated.len(), 2);
+        assert_eq!(rest, (String::from("b"), String::from("c")));
+    }
+
+    #[test]
+    fn lazy_retries_until_the_rest_of_a_tuple_matches() {
+        assert!((Lazy(..ANY), "b", "c").matches(&"aabc"));
+        assert!((Lazy(..=ANY), "b", "c").matches(&"aabc"));
+        assert!((Lazy(..ANY), "b", "c").matches(&"bc"));
+        assert!(!(Lazy(..=ANY), "b", "c").matches(&"bc"));
+
+        let pattern = (Lazy(..=ANY), "b", "c");
+        let mut reference = "aabcbc".chars().peekable();
+        let dest = RefCell::new(Default::default());
+        assert!(pattern.consume_with_dest(&mut reference, Some(&dest)));
+        assert_eq!(reference.collect::<String>(), "bc");
+        let (repeated, b, c) = dest.into_inner();
+        assert_eq!(repeated.len(), 2);
+        assert_eq!((b, c), (String::from("b"), String::from("c")));
+
+        let mut reference = "aaxc".chars().peekable();
+        let dest = RefCell::new(Default::default());
+        assert!(!pattern.consume_with_dest(&mut reference, Some(&dest)));
+        assert_eq!(reference.collect::<String>(), "aaxc");
+        let (repeated, b, c) = dest.into_inner();
+        assert!(repeated.is_empty());
+        assert_eq!((b, c), (String::new(), String::new()));
+    }
+
+    #[test]
+    fn format_pattern_leaves_input_and_dest_on_mismatch() {
+        let pattern = crate::fmt_pat!("user_{}", 42);
+        let mut reference = "user_9".chars().peekable();
+        let dest = RefCell::new(String::new());
+        assert!(!pattern.consume_with_dest(&mut reference, Some(&dest)));
+        assert_eq!(reference.collect::<String>(), "user_9");
+        assert_eq!(dest.into_inner(), "");
+
+        let mut reference = "user_42!".chars().peekable();
+        let dest = RefCell::new(String::new());
+        assert!(pattern.consume_with_dest(&mut reference, Some(&dest)));
+        assert_eq!(reference.collect::<String>(), "!");
+        assert_eq!(dest.into_inner(), "user_42");
+    }
+
+    #[test]
+    fn multiline_input_needs_no_special_handling() {
+        // Kept in sync with the "Multi-line input" example in the README
+        use crate::matches;
+        const ANY: fn(char) -> bool = |_| true;
+        const BODY: RepeatUntil<fn(char) -> bool, &str> = RepeatUntil(ANY, "*/");
+
+        assert!(matches!("/* first line\n   second line */" => "/*", BODY, "*/"));
+        assert!(matches!("key =\n    value" => ALPHABETIC, WS, "=", WS, ALPHABETIC));
+    }
+}
@@ -1,4 +1,6 @@
-use std::cell::{RefCell, RefMut};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
 
 /// A destination that can store matched items.
 /// This is a wrapper around `RefCell<T>` to allow interior mutability
@@ -28,6 +30,53 @@ impl<T> Dest<T> {
     }
 }
 
+impl<S, P> Dest<Vec<(S, P)>> {
+    /// Split captured `(separator, element)` pairs into separate destinations.
+    pub fn split_dest(self) -> (Dest<Vec<S>>, Dest<Vec<P>>) {
+        let (seps, elems): (Vec<S>, Vec<P>) = self.into_inner().into_iter().unzip();
+        (seps.into(), elems.into())
+    }
+}
+
+impl<T> IntoIterator for Dest<Vec<T>> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.into_inner().into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Dest<Vec<T>> {
+    type Item = Ref<'a, T>;
+    type IntoIter = DestIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        DestIter {
+            inner: &self.inner,
+            index: 0,
+        }
+    }
+}
+
+/// An iterator over the items captured in a `Dest<Vec<T>>`, created by iterating
+/// `&Dest<Vec<T>>`. Each item borrows the destination, so it must be dropped before
+/// the destination is written to again.
+pub struct DestIter<'a, T> {
+    inner: &'a RefCell<Vec<T>>,
+    index: usize,
+}
+
+impl<'a, T> Iterator for DestIter<'a, T> {
+    type Item = Ref<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = Ref::filter_map(self.inner.borrow(), |v| v.get(self.index)).ok()?;
+        self.index += 1;
+        Some(item)
+    }
+}
+
 impl<T> From<T> for Dest<T> {
     fn from(value: T) -> Self {
         Dest {
@@ -54,6 +103,35 @@ where
     }
 }
 
+impl<T> PartialEq<T> for Dest<T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &T) -> bool {
+        *self.inner.borrow() == *other
+    }
+}
+
+impl<T> PartialEq for Dest<T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        *self.inner.borrow() == *other.inner.borrow()
+    }
+}
+
+impl<T> Eq for Dest<T> where T: Eq {}
+
+impl<T> PartialOrd<T> for Dest<T>
+where
+    T: PartialOrd,
+{
+    fn partial_cmp(&self, other: &T) -> Option<std::cmp::Ordering> {
+        self.inner.borrow().partial_cmp(other)
+    }
+}
+
 impl<Item, T> crate::base::Destination<Item> for Dest<T>
 where
     T: crate::base::Destination<Item>,
@@ -62,3 +140,438 @@ where
         self.inner.borrow_mut().pickup(item)
     }
 }
+
+impl<T> Dest<T> {
+    /// Wrap the destination so that items rejected by `f` are silently dropped.
+    pub fn with_validation<F>(self, f: F) -> ValidatedDest<T, F> {
+        ValidatedDest(self, f)
+    }
+}
+
+/// A destination that only stores items accepted by a predicate.
+/// The predicate sees each element before it is stored, e.g. each `String` of a `Vec<String>`.
+pub struct ValidatedDest<T, F>(pub Dest<T>, pub F);
+
+impl<T, F> ValidatedDest<T, F> {
+    pub fn as_dest(&self) -> &Dest<T> {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+}
+
+impl<T, F> Clone for ValidatedDest<T, F>
+where
+    T: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        ValidatedDest(self.0.clone(), self.1.clone())
+    }
+}
+
+/// The default validator accepts every item. Using a plain `fn` pointer for the predicate
+/// lets the destination be created by combinators that need a fresh, empty value.
+impl<T, E> Default for ValidatedDest<T, fn(&E) -> bool>
+where
+    T: Default,
+{
+    fn default() -> Self {
+        ValidatedDest(Dest::new(), |_| true)
+    }
+}
+
+impl<T, F> std::fmt::Debug for ValidatedDest<T, F>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ValidatedDest").field(&self.0).finish()
+    }
+}
+
+impl<T, F> crate::base::Destination<&T> for ValidatedDest<Vec<T>, F>
+where
+    T: Clone,
+    F: Fn(&T) -> bool,
+{
+    fn pickup(&mut self, item: &T) {
+        if (self.1)(item) {
+            self.0.borrow_mut().push(item.clone());
+        }
+    }
+}
+
+impl<T, F> crate::base::Destination<char> for ValidatedDest<Vec<T>, F> where
+    T: crate::base::Destination<char>
+{
+}
+
+impl<F> crate::base::Destination<char> for ValidatedDest<String, F>
+where
+    F: Fn(&char) -> bool,
+{
+    fn pickup(&mut self, item: char) {
+        if (self.1)(&item) {
+            self.0.borrow_mut().push(item);
+        }
+    }
+}
+
+impl<Inner, Item, F> crate::base::Collector<Inner, Item> for ValidatedDest<Vec<Inner>, F>
+where
+    Inner: crate::base::Destination<Item> + Default + Clone,
+    F: Fn(&Inner) -> bool,
+{
+    fn commit(out: &RefCell<Self>, captured: Inner) {
+        let out = out.borrow();
+        if (out.1)(&captured) {
+            out.0.borrow_mut().push(captured);
+        }
+    }
+}
+
+/// A fixed-capacity buffer that keeps only the most recent items, dropping the oldest
+/// once `capacity` is exceeded.
+#[derive(Clone, Debug)]
+pub struct Ring<T> {
+    buf: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T> Ring<T> {
+    pub fn new(capacity: usize) -> Self {
+        Ring {
+            buf: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Iterate the items oldest-first.
+    pub fn iter(&self) -> std::collections::vec_deque::Iter<'_, T> {
+        self.buf.iter()
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.buf.push_back(item);
+        while self.buf.len() > self.capacity {
+            self.buf.pop_front();
+        }
+    }
+}
+
+/// A default ring has no capacity limit.
+impl<T> Default for Ring<T> {
+    fn default() -> Self {
+        Ring {
+            buf: VecDeque::new(),
+            capacity: usize::MAX,
+        }
+    }
+}
+
+impl<T> IntoIterator for Ring<T> {
+    type Item = T;
+    type IntoIter = std::collections::vec_deque::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.buf.into_iter()
+    }
+}
+
+impl<T> crate::base::Destination<&T> for Ring<T>
+where
+    T: Clone,
+{
+    fn pickup(&mut self, item: &T) {
+        self.push(item.clone());
+    }
+}
+
+impl<T> crate::base::Destination<char> for Ring<T> where T: crate::base::Destination<char> {}
+
+impl<Inner, Item> crate::base::Collector<Inner, Item> for Ring<Inner>
+where
+    Inner: crate::base::Destination<Item> + Default + Clone,
+{
+    fn commit(out: &RefCell<Self>, captured: Inner) {
+        out.borrow_mut().push(captured);
+    }
+}
+
+/// Types whose size can be checked before storing another item.
+pub trait Len {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Len for Vec<T> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+}
+
+impl Len for String {
+    fn len(&self) -> usize {
+        String::len(self)
+    }
+}
+
+impl<T> Len for VecDeque<T> {
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+}
+
+impl<T> Len for Ring<T> {
+    fn len(&self) -> usize {
+        Ring::len(self)
+    }
+}
+
+impl<T> Dest<T> {
+    /// Cap the destination at `max` items; anything captured past that is dropped.
+    pub fn bounded(self, max: usize) -> BoundedDest<T> {
+        BoundedDest(self, max)
+    }
+}
+
+/// A destination that stops storing items once its length reaches a limit, so crafted
+/// input cannot make a capture grow without bound. For a `String` the limit is in bytes.
+pub struct BoundedDest<T>(pub Dest<T>, pub usize);
+
+impl<T> BoundedDest<T> {
+    pub fn as_dest(&self) -> &Dest<T> {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+}
+
+impl<T> BoundedDest<T>
+where
+    T: Len,
+{
+    /// Whether the limit has been reached, i.e. further items would be dropped.
+    pub fn is_full(&self) -> bool {
+        self.0.borrow_mut().len() >= self.1
+    }
+}
+
+impl<T> Clone for BoundedDest<T>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        BoundedDest(self.0.clone(), self.1)
+    }
+}
+
+/// A default bounded destination has no limit.
+impl<T> Default for BoundedDest<T>
+where
+    T: Default,
+{
+    fn default() -> Self {
+        BoundedDest(Dest::new(), usize::MAX)
+    }
+}
+
+impl<T> std::fmt::Debug for BoundedDest<T>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("BoundedDest")
+            .field(&self.0)
+            .field(&self.1)
+            .finish()
+    }
+}
+
+impl<Item, T> crate::base::Destination<Item> for BoundedDest<T>
+where
+    T: crate::base::Destination<Item> + Len,
+{
+    fn pickup(&mut self, item: Item) {
+        if !self.is_full() {
+            self.0.borrow_mut().pickup(item);
+        }
+    }
+}
+
+impl<Inner, Item> crate::base::Collector<Inner, Item> for BoundedDest<Vec<Inner>>
+where
+    Inner: crate::base::Destination<Item> + Default + Clone,
+{
+    fn commit(out: &RefCell<Self>, captured: Inner) {
+        let out = out.borrow();
+        if !out.is_full() {
+            out.0.borrow_mut().push(captured);
+        }
+    }
+}
+
+/// A collection that keeps only the first occurrence of each item, in insertion order.
+/// Lookups are a linear scan, which suits the small sets typical of captures.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dedup<T>(Vec<T>);
+
+impl<T> Default for Dedup<T> {
+    fn default() -> Self {
+        Dedup(Vec::new())
+    }
+}
+
+impl<T> Dedup<T>
+where
+    T: PartialEq,
+{
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Insert `item` unless an equal item is already present. Returns whether it was added.
+    pub fn insert(&mut self, item: T) -> bool {
+        if self.0.contains(&item) {
+            return false;
+        }
+        self.0.push(item);
+        true
+    }
+}
+
+impl<T> Dedup<T> {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate the unique items in insertion order.
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.0.iter()
+    }
+}
+
+impl<T> IntoIterator for Dedup<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<T> Len for Dedup<T> {
+    fn len(&self) -> usize {
+        Dedup::len(self)
+    }
+}
+
+impl<T> crate::base::Destination<&T> for Dedup<T>
+where
+    T: PartialEq + Clone,
+{
+    fn pickup(&mut self, item: &T) {
+        self.insert(item.clone());
+    }
+}
+
+impl<T> crate::base::Destination<char> for Dedup<T> where T: crate::base::Destination<char> {}
+
+impl<Inner, Item> crate::base::Collector<Inner, Item> for Dedup<Inner>
+where
+    Inner: crate::base::Destination<Item> + Default + Clone + PartialEq,
+{
+    fn commit(out: &RefCell<Self>, captured: Inner) {
+        out.borrow_mut().insert(captured);
+    }
+}
+
+/// A destination that captures each distinct value once.
+pub type DedupDest<T> = Dest<Dedup<T>>;
+
+/// A destination that counts captures instead of storing them. Used with `To`, each
+/// committed capture of type `T` counts once; picking up raw items counts each item.
+pub struct Counter<T> {
+    count: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Counter<T> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<T> Default for Counter<T> {
+    fn default() -> Self {
+        Counter {
+            count: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Counter<T> {
+    fn clone(&self) -> Self {
+        Counter {
+            count: self.count,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Counter<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Counter").field(&self.count).finish()
+    }
+}
+
+impl<Item, T> crate::base::Destination<Item> for Counter<T> {
+    fn pickup(&mut self, _item: Item) {
+        self.count += 1;
+    }
+}
+
+impl<Inner, Item> crate::base::Collector<Inner, Item> for Counter<Inner> {
+    fn commit(out: &RefCell<Self>, _captured: Inner) {
+        out.borrow_mut().count += 1;
+    }
+}
+
+impl<T> Dest<Counter<T>> {
+    /// The number of captures counted so far.
+    pub fn count(&self) -> usize {
+        self.inner.borrow().count()
+    }
+}
+
+/// A destination that counts captures without storing them.
+pub type CountingDest<T> = Dest<Counter<T>>;
@@ -1,13 +1,46 @@
-use crate::base::{Destination, Pattern, PeekableExt, Satisfies};
+use crate::base::{Destination, Pattern, PeekableExt, Satisfies, SepN};
 use std::cell::RefCell;
+use std::num::NonZeroUsize;
+
+#[cfg(feature = "proc-macro2")]
+use crate::base::TokenTreeItem;
 
 pub struct Token<Ref, Dest> {
     pub predicate: fn(&Ref) -> bool,
     pub parser: fn(Vec<Ref>) -> Dest,
     pub at_least: usize,
+    /// The most items to consume, or `None` for as many as the predicate accepts.
+    pub at_most: Option<usize>,
     pub skip_leading: Option<fn(&Ref) -> bool>,
+    /// Applied to the result of `parser`, e.g. `UPPERCASE`.
+    pub transform: Option<fn(Dest) -> Dest>,
+}
+
+impl<Ref, D> Token<Ref, D> {
+    /// Limit the token to exactly one item, so a run of matching items is not taken
+    /// as a whole.
+    pub const fn single(self) -> Self {
+        Token {
+            at_least: 1,
+            at_most: Some(1),
+            ..self
+        }
+    }
+
+    /// Post-process every capture with `f`, e.g. `ALPHABETIC.with_transform(UPPERCASE)`.
+    pub const fn with_transform(self, f: fn(D) -> D) -> Self {
+        Token {
+            transform: Some(f),
+            ..self
+        }
+    }
 }
 
+/// Transforms for `Token::with_transform` on tokens that capture a `String`.
+pub const UPPERCASE: fn(String) -> String = |s| s.to_uppercase();
+pub const LOWERCASE: fn(String) -> String = |s| s.to_lowercase();
+pub const TRIM: fn(String) -> String = |s| s.trim().to_string();
+
 impl<'a, Reference, RefT, D> Pattern<'a, Reference> for Token<RefT, D>
 where
     Reference: Iterator<Item = RefT> + Clone + PeekableExt,
@@ -19,6 +52,14 @@ where
         core::iter::empty()
     }
 
+    fn explain(&self) -> String {
+        format!("token of at least {} items", self.at_least)
+    }
+
+    fn matches_empty(&self) -> bool {
+        self.at_least == 0
+    }
+
     fn consume_with_dest(
         &'a self,
         reference: &mut Reference,
@@ -39,7 +80,10 @@ where
         }
         let mut collected: Vec<RefT> = Vec::new();
 
-        while let Some(peeked) = trial.peek() {
+        let at_most = self.at_most.unwrap_or(usize::MAX);
+        while collected.len() < at_most
+            && let Some(peeked) = trial.peek()
+        {
             if (self.predicate)(peeked) {
                 if let Some(next_item) = trial.next() {
                     collected.push(next_item);
@@ -54,26 +98,15 @@ where
         if collected.len() < self.at_least {
             return false;
         }
-        let mut advance = collected.len();
-        if let Some(skip) = self.skip_leading {
-            let mut temp = reference.clone();
-            let mut skipped = 0usize;
-            while let Some(p) = temp.peek() {
-                if skip(p) {
-                    let _ = temp.next();
-                    skipped += 1;
-                    continue;
-                }
-                break;
-            }
-            advance += skipped;
-        }
-        for _ in 0..advance {
-            reference.next();
-        }
+        // `trial` has already moved past both the skipped and the collected items
+        *reference = trial;
 
         // Parse the collected slice into the destination value
         let parsed = (self.parser)(collected);
+        let parsed = match self.transform {
+            Some(transform) => transform(parsed),
+            None => parsed,
+        };
 
         if let Some(dref) = dest {
             *dref.borrow_mut() = parsed;
@@ -87,7 +120,7 @@ impl Destination<char> for usize {}
 
 // Numeric token helpers (parametric by base `N`).
 fn pred_num<const N: u32>(ch: &char) -> bool {
-    ch.to_digit(N).is_some()
+    ch.is_digit(N)
 }
 
 fn parse_num<const N: u32>(v: Vec<char>) -> usize {
@@ -102,7 +135,9 @@ const fn make_num<const N: u32>() -> Token<char, usize> {
         predicate: pred_num::<N>,
         parser: parse_num::<N>,
         at_least: 1,
+        at_most: None,
         skip_leading: None,
+        transform: None,
     }
 }
 
@@ -111,23 +146,1015 @@ pub const HEX: Token<char, usize> = make_num::<16>();
 pub const OCT: Token<char, usize> = make_num::<8>();
 pub const BIN: Token<char, usize> = make_num::<2>();
 
+/// Four decimal numbers separated by dots, as in an IPv4 address. Captures the four numbers.
+pub const SEP4: SepN<&str, Token<char, usize>, 4> = SepN(".", NUM);
+
 pub const WS: Token<char, ()> = Token {
     predicate: |ch| ch.is_whitespace(),
     parser: |_| (),
     at_least: 1,
+    at_most: None,
+    skip_leading: None,
+    transform: None,
+};
+
+/// Zero or more whitespace characters.
+pub const WS_OPT: Token<char, ()> = Token {
+    predicate: |ch| ch.is_whitespace(),
+    parser: |_| (),
+    at_least: 0,
+    at_most: None,
     skip_leading: None,
+    transform: None,
 };
 
+impl<D> Token<char, D> {
+    /// Wrap the token so that any surrounding whitespace is consumed and discarded.
+    pub const fn trimmed(self) -> (Token<char, ()>, Self, Token<char, ()>) {
+        (WS_OPT, self, WS_OPT)
+    }
+}
+
 pub const ALPHABETIC: Token<char, String> = Token {
     predicate: |ch| ch.is_alphabetic(),
     parser: |v| v.into_iter().collect(),
     at_least: 1,
+    at_most: None,
     skip_leading: None,
+    transform: None,
 };
 
 pub const ALPHANUMERIC: Token<char, String> = Token {
     predicate: |ch| ch.is_alphanumeric(),
     parser: |v| v.into_iter().collect(),
     at_least: 1,
+    at_most: None,
     skip_leading: None,
+    transform: None,
+};
+
+/// A token whose parser may reject the collected items, failing the pattern without
+/// consuming anything. The capture is `Some` after a match, so the value type needs no
+/// `Default` of its own.
+pub struct GuardedToken<Ref, Dest> {
+    pub predicate: fn(&Ref) -> bool,
+    pub parser: fn(Vec<Ref>) -> Option<Dest>,
+    pub at_least: usize,
+}
+
+impl<'a, Reference, RefT, D> Pattern<'a, Reference> for GuardedToken<RefT, D>
+where
+    Reference: Iterator<Item = RefT> + Clone + PeekableExt,
+{
+    type Iter = core::iter::Empty<Reference::Item>;
+    type Dest = Option<D>;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        format!("guarded token of at least {} items", self.at_least)
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        let mut collected: Vec<RefT> = Vec::new();
+        while let Some(peeked) = trial.peek() {
+            if !(self.predicate)(peeked) {
+                break;
+            }
+            match trial.next() {
+                Some(item) => collected.push(item),
+                None => break,
+            }
+        }
+        if collected.len() < self.at_least {
+            return false;
+        }
+        let Some(parsed) = (self.parser)(collected) else {
+            return false;
+        };
+        *reference = trial;
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = Some(parsed);
+        }
+        true
+    }
+}
+
+impl Destination<char> for NonZeroUsize {}
+
+const fn make_non_zero_num<const N: u32>() -> GuardedToken<char, NonZeroUsize> {
+    GuardedToken {
+        predicate: pred_num::<N>,
+        parser: |v| NonZeroUsize::new(parse_num::<N>(v)),
+        at_least: 1,
+    }
+}
+
+/// A decimal integer other than zero.
+pub const NON_ZERO_NUM: GuardedToken<char, NonZeroUsize> = make_non_zero_num::<10>();
+/// A hexadecimal integer other than zero.
+pub const NON_ZERO_HEX: GuardedToken<char, NonZeroUsize> = make_non_zero_num::<16>();
+
+impl Destination<char> for f64 {}
+
+/// Matches a decimal floating-point number such as `3.14`, `-0.5` or `1e-10`: an optional
+/// sign, digits with an optional fractional part, and an optional exponent. A `.` or `e`
+/// that is not followed by digits is left unconsumed, so `5.` matches `5` alone.
+pub struct FloatToken {
+    /// Reject numbers without a fractional part, such as `3` or `1e5`.
+    pub require_point: bool,
+}
+
+pub const FLOAT: FloatToken = FloatToken {
+    require_point: false,
+};
+/// Like `FLOAT`, but the number must have a decimal point.
+pub const STRICT_FLOAT: FloatToken = FloatToken {
+    require_point: true,
+};
+
+/// Append the run of decimal digits at the front of `reference` to `text`, returning
+/// how many there were.
+fn push_digits<R>(reference: &mut R, text: &mut String) -> usize
+where
+    R: Iterator<Item = char> + PeekableExt,
+{
+    let mut count = 0;
+    while let Some(&ch) = reference.peek() {
+        if !ch.is_ascii_digit() {
+            break;
+        }
+        text.push(ch);
+        reference.next();
+        count += 1;
+    }
+    count
+}
+
+/// Take `marker`, an optional sign if `signed`, and at least one digit, or nothing at all.
+fn push_suffix<R>(reference: &mut R, text: &mut String, marker: &[char], signed: bool) -> bool
+where
+    R: Iterator<Item = char> + Clone + PeekableExt,
+{
+    let mut trial = reference.clone();
+    let mut suffix = String::new();
+    match trial.next() {
+        Some(ch) if marker.contains(&ch) => suffix.push(ch),
+        _ => return false,
+    }
+    if let Some(&sign @ ('+' | '-')) = trial.peek().filter(|_| signed) {
+        suffix.push(sign);
+        trial.next();
+    }
+    if push_digits(&mut trial, &mut suffix) == 0 {
+        return false;
+    }
+    text.push_str(&suffix);
+    *reference = trial;
+    true
+}
+
+fn take_float<R>(reference: &mut R, require_point: bool) -> Option<f64>
+where
+    R: Iterator<Item = char> + Clone + PeekableExt,
+{
+    let mut text = String::new();
+    if let Some(&sign @ ('+' | '-')) = reference.peek() {
+        text.push(sign);
+        reference.next();
+    }
+    let whole = push_digits(reference, &mut text);
+    let point = push_suffix(reference, &mut text, &['.'], false);
+    if !point && (whole == 0 || require_point) {
+        return None;
+    }
+    push_suffix(reference, &mut text, &['e', 'E'], true);
+    text.parse().ok()
+}
+
+impl<'a, Reference> Pattern<'a, Reference> for FloatToken
+where
+    Reference: Iterator<Item = char> + Clone + PeekableExt,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = f64;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        if self.require_point {
+            "decimal number with a point".to_string()
+        } else {
+            "decimal number".to_string()
+        }
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        let Some(value) = take_float(&mut trial, self.require_point) else {
+            return false;
+        };
+        *reference = trial;
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = value;
+        }
+        true
+    }
+}
+
+impl Destination<char> for i64 {}
+
+/// Matches a decimal integer with an optional leading `-`, such as `42` or `-7`. A `-`
+/// without digits after it does not match, and values out of range saturate to
+/// `i64::MIN` or `i64::MAX`.
+pub struct SignedIntToken;
+
+pub const SIGNED_INT: SignedIntToken = SignedIntToken;
+
+impl<'a, Reference> Pattern<'a, Reference> for SignedIntToken
+where
+    Reference: Iterator<Item = char> + Clone + PeekableExt,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = i64;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        "signed integer".to_string()
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        let negative = trial.peek() == Some(&'-');
+        if negative {
+            trial.next();
+        }
+        let mut digits = String::new();
+        if push_digits(&mut trial, &mut digits) == 0 {
+            return false;
+        }
+        // Accumulate towards the sign so that `i64::MIN` itself is reachable
+        let value = digits.chars().fold(0i64, |acc, ch| {
+            let digit = i64::from(ch.to_digit(10).unwrap());
+            if negative {
+                acc.saturating_mul(10).saturating_sub(digit)
+            } else {
+                acc.saturating_mul(10).saturating_add(digit)
+            }
+        });
+        *reference = trial;
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = value;
+        }
+        true
+    }
+}
+
+/// A Rust integer literal, tagged by its type suffix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntLit {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    Usize(usize),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Isize(isize),
+    Unsuffixed(u64),
+}
+
+impl Default for IntLit {
+    fn default() -> Self {
+        IntLit::Unsuffixed(0)
+    }
+}
+
+impl Destination<char> for IntLit {}
+
+/// Matches a Rust integer literal such as `1_000u32`: digits with optional `_` grouping,
+/// followed by an optional type suffix. Fails without consuming if the value does not fit.
+pub struct RustInt;
+
+pub const RUST_INT: RustInt = RustInt;
+
+const INT_SUFFIXES: [&str; 10] = [
+    "usize", "isize", "u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64",
+];
+
+impl<'a, Reference> Pattern<'a, Reference> for RustInt
+where
+    Reference: Iterator<Item = char> + Clone + PeekableExt,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = IntLit;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        "Rust integer literal".to_string()
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        let mut value: Option<u64> = Some(0);
+        let mut digits = 0usize;
+
+        loop {
+            match trial.peek() {
+                Some(ch) if ch.is_ascii_digit() => {
+                    let digit = u64::from(ch.to_digit(10).unwrap());
+                    value = value
+                        .and_then(|v| v.checked_mul(10))
+                        .and_then(|v| v.checked_add(digit));
+                    digits += 1;
+                    trial.next();
+                }
+                // `_` only groups digits, so it must be followed by another digit
+                Some('_') if digits > 0 => {
+                    let mut after = trial.clone();
+                    after.next();
+                    if !after.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+                        break;
+                    }
+                    trial = after;
+                }
+                _ => break,
+            }
+        }
+        if digits == 0 {
+            return false;
+        }
+        let Some(value) = value else {
+            return false;
+        };
+
+        let mut suffix = None;
+        for candidate in INT_SUFFIXES {
+            let mut after = trial.clone();
+            if candidate.chars().all(|c| after.next() == Some(c)) {
+                suffix = Some(candidate);
+                trial = after;
+                break;
+            }
+        }
+
+        let lit = match suffix {
+            Some("u8") => u8::try_from(value).ok().map(IntLit::U8),
+            Some("u16") => u16::try_from(value).ok().map(IntLit::U16),
+            Some("u32") => u32::try_from(value).ok().map(IntLit::U32),
+            Some("u64") => Some(IntLit::U64(value)),
+            Some("usize") => usize::try_from(value).ok().map(IntLit::Usize),
+            Some("i8") => i8::try_from(value).ok().map(IntLit::I8),
+            Some("i16") => i16::try_from(value).ok().map(IntLit::I16),
+            Some("i32") => i32::try_from(value).ok().map(IntLit::I32),
+            Some("i64") => i64::try_from(value).ok().map(IntLit::I64),
+            Some("isize") => isize::try_from(value).ok().map(IntLit::Isize),
+            _ => Some(IntLit::Unsuffixed(value)),
+        };
+        let Some(lit) = lit else {
+            return false;
+        };
+
+        *reference = trial;
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = lit;
+        }
+        true
+    }
+}
+
+/// An RGB color with eight bits per channel.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Destination<char> for Color {}
+
+/// Matches a hex color code, `#RRGGBB` or the short form `#RGB`, whose digits each
+/// expand to a full channel (`#F0A` is `#FF00AA`). The long form is tried first.
+pub struct HexColor;
+
+pub const HEX_COLOR: HexColor = HexColor;
+
+impl<'a, Reference> Pattern<'a, Reference> for HexColor
+where
+    Reference: Iterator<Item = char> + Clone + PeekableExt,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = Color;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        "hex color".to_string()
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut trial = reference.clone();
+        if trial.next() != Some('#') {
+            return false;
+        }
+
+        let mut nibbles = Vec::with_capacity(6);
+        let mut after_short = None;
+        while nibbles.len() < 6 {
+            match trial.peek().and_then(|ch| ch.to_digit(16)) {
+                Some(digit) => {
+                    nibbles.push(digit as u8);
+                    trial.next();
+                }
+                None => break,
+            }
+            if nibbles.len() == 3 {
+                after_short = Some(trial.clone());
+            }
+        }
+
+        let color = if nibbles.len() == 6 {
+            Color {
+                r: nibbles[0] << 4 | nibbles[1],
+                g: nibbles[2] << 4 | nibbles[3],
+                b: nibbles[4] << 4 | nibbles[5],
+            }
+        } else if let Some(after) = after_short {
+            trial = after;
+            Color {
+                r: nibbles[0] * 0x11,
+                g: nibbles[1] * 0x11,
+                b: nibbles[2] * 0x11,
+            }
+        } else {
+            return false;
+        };
+
+        *reference = trial;
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = color;
+        }
+        true
+    }
+}
+
+/// A calendar date as captured by [`ISO_DATE`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+/// A time of day as captured by [`ISO_TIME`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+/// A date and time as captured by [`ISO_DATETIME`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DateTime {
+    pub date: Date,
+    pub time: Time,
+}
+
+impl Destination<char> for Date {}
+impl Destination<char> for Time {}
+impl Destination<char> for DateTime {}
+
+/// Matches a `YYYY-MM-DD` date with month in `1..=12` and day in `1..=31`. The day is not
+/// checked against the length of the month.
+pub struct IsoDateToken;
+
+/// Matches a `HH:MM:SS` time with hour in `0..=23` and minute and second in `0..=59`.
+pub struct IsoTimeToken;
+
+/// Matches a date and a time joined by `T`, as in `2024-01-15T14:30:00`.
+pub struct IsoDateTimeToken;
+
+pub const ISO_DATE: IsoDateToken = IsoDateToken;
+pub const ISO_TIME: IsoTimeToken = IsoTimeToken;
+pub const ISO_DATETIME: IsoDateTimeToken = IsoDateTimeToken;
+
+fn take_digits<R: Iterator<Item = char>>(reference: &mut R, count: usize) -> Option<u32> {
+    let mut value = 0;
+    for _ in 0..count {
+        value = value * 10 + reference.next()?.to_digit(10)?;
+    }
+    Some(value)
+}
+
+fn take_date<R: Iterator<Item = char>>(reference: &mut R) -> Option<Date> {
+    let year = take_digits(reference, 4)?;
+    (reference.next()? == '-').then_some(())?;
+    let month = take_digits(reference, 2)?;
+    (reference.next()? == '-').then_some(())?;
+    let day = take_digits(reference, 2)?;
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some(Date {
+        year: year as u16,
+        month: month as u8,
+        day: day as u8,
+    })
+}
+
+fn take_time<R: Iterator<Item = char>>(reference: &mut R) -> Option<Time> {
+    let hour = take_digits(reference, 2)?;
+    (reference.next()? == ':').then_some(())?;
+    let minute = take_digits(reference, 2)?;
+    (reference.next()? == ':').then_some(())?;
+    let second = take_digits(reference, 2)?;
+    (hour < 24 && minute < 60 && second < 60).then_some(Time {
+        hour: hour as u8,
+        minute: minute as u8,
+        second: second as u8,
+    })
+}
+
+fn take_datetime<R: Iterator<Item = char>>(reference: &mut R) -> Option<DateTime> {
+    let date = take_date(reference)?;
+    (reference.next()? == 'T').then_some(())?;
+    let time = take_time(reference)?;
+    Some(DateTime { date, time })
+}
+
+macro_rules! impl_pattern_iso {
+    ($token:ty, $dest:ty, $take:ident, $explain:literal) => {
+        impl<'a, Reference> Pattern<'a, Reference> for $token
+        where
+            Reference: Iterator<Item = char> + Clone,
+        {
+            type Iter = core::iter::Empty<char>;
+            type Dest = $dest;
+
+            fn get_iter(&'a self) -> Self::Iter {
+                core::iter::empty()
+            }
+
+            fn explain(&self) -> String {
+                $explain.to_string()
+            }
+
+            fn consume_with_dest(
+                &'a self,
+                reference: &mut Reference,
+                dest: Option<&RefCell<Self::Dest>>,
+            ) -> bool
+            where
+                Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+            {
+                let mut trial = reference.clone();
+                let Some(value) = $take(&mut trial) else {
+                    return false;
+                };
+                *reference = trial;
+                if let Some(dref) = dest {
+                    *dref.borrow_mut() = value;
+                }
+                true
+            }
+        }
+    };
+}
+
+impl_pattern_iso!(IsoDateToken, Date, take_date, "ISO 8601 date");
+impl_pattern_iso!(IsoTimeToken, Time, take_time, "ISO 8601 time");
+impl_pattern_iso!(
+    IsoDateTimeToken,
+    DateTime,
+    take_datetime,
+    "ISO 8601 date and time"
+);
+
+/// Matches one or more characters belonging to a Unicode character class, collecting them
+/// into a `String`.
+#[derive(Clone, Copy)]
+pub struct UnicodeClass {
+    pub category: fn(char) -> bool,
+}
+
+impl<'a, Reference> Pattern<'a, Reference> for UnicodeClass
+where
+    Reference: Iterator<Item = char> + Clone + PeekableExt,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = String;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        "unicode class".to_string()
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let mut collected = String::new();
+        while let Some(&ch) = reference.peek() {
+            if !(self.category)(ch) {
+                break;
+            }
+            collected.push(ch);
+            reference.next();
+        }
+        if collected.is_empty() {
+            return false;
+        }
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = collected;
+        }
+        true
+    }
+}
+
+// With the `unicode` feature the classes follow the Unicode general categories exactly;
+// without it they fall back to the closest checks available in `core`.
+#[cfg(feature = "unicode")]
+fn general_category_is(ch: char, major: char) -> bool {
+    unicode_general_category::get_general_category(ch)
+        .abbreviation()
+        .starts_with(major)
+}
+
+#[cfg(feature = "unicode")]
+fn is_unicode_letter(ch: char) -> bool {
+    general_category_is(ch, 'L')
+}
+
+#[cfg(not(feature = "unicode"))]
+fn is_unicode_letter(ch: char) -> bool {
+    ch.is_alphabetic()
+}
+
+#[cfg(feature = "unicode")]
+fn is_unicode_number(ch: char) -> bool {
+    general_category_is(ch, 'N')
+}
+
+#[cfg(not(feature = "unicode"))]
+fn is_unicode_number(ch: char) -> bool {
+    ch.is_numeric()
+}
+
+#[cfg(feature = "unicode")]
+fn is_unicode_punctuation(ch: char) -> bool {
+    general_category_is(ch, 'P')
+}
+
+#[cfg(not(feature = "unicode"))]
+fn is_unicode_punctuation(ch: char) -> bool {
+    ch.is_ascii_punctuation() && !is_unicode_symbol(ch)
+}
+
+#[cfg(feature = "unicode")]
+fn is_unicode_symbol(ch: char) -> bool {
+    general_category_is(ch, 'S')
+}
+
+#[cfg(not(feature = "unicode"))]
+fn is_unicode_symbol(ch: char) -> bool {
+    "$+<=>^`|~".contains(ch)
+}
+
+/// Letters (`L*`). Without the `unicode` feature, any alphabetic character.
+pub const UNICODE_LETTER: UnicodeClass = UnicodeClass {
+    category: is_unicode_letter,
+};
+
+/// Numbers (`N*`).
+pub const UNICODE_NUMBER: UnicodeClass = UnicodeClass {
+    category: is_unicode_number,
+};
+
+/// Punctuation (`P*`). Without the `unicode` feature, ASCII punctuation only.
+pub const UNICODE_PUNCTUATION: UnicodeClass = UnicodeClass {
+    category: is_unicode_punctuation,
+};
+
+/// Symbols (`S*`). Without the `unicode` feature, ASCII symbols only.
+pub const UNICODE_SYMBOL: UnicodeClass = UnicodeClass {
+    category: is_unicode_symbol,
 };
+
+/// Matches a single extended grapheme cluster, collecting it into a `String`.
+#[cfg(feature = "unicode-segmentation")]
+pub struct Grapheme;
+
+#[cfg(feature = "unicode-segmentation")]
+pub const GRAPHEME: Grapheme = Grapheme;
+
+#[cfg(feature = "unicode-segmentation")]
+impl<'a, Reference> Pattern<'a, Reference> for Grapheme
+where
+    Reference: Iterator<Item = char> + Clone + PeekableExt,
+{
+    type Iter = core::iter::Empty<char>;
+    type Dest = String;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        "grapheme cluster".to_string()
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        // A boundary only depends on the characters before it and the one right after,
+        // so the first cluster is complete as soon as a second one starts.
+        let mut lookahead = reference.clone();
+        let mut buffer = String::new();
+        let mut cluster_len = 0usize;
+        for ch in lookahead.by_ref() {
+            buffer.push(ch);
+            if buffer.graphemes(true).nth(1).is_some() {
+                break;
+            }
+            cluster_len += 1;
+        }
+        if cluster_len == 0 {
+            return false;
+        }
+
+        let cluster: String = reference.by_ref().take(cluster_len).collect();
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = cluster;
+        }
+        true
+    }
+}
+
+/// A peekable, cloneable iterator over the grapheme clusters of a string slice, for
+/// walking input one user-perceived character at a time.
+#[cfg(feature = "unicode-segmentation")]
+#[derive(Clone)]
+pub struct GraphemeCheckpoint<'a> {
+    inner: std::iter::Peekable<unicode_segmentation::Graphemes<'a>>,
+}
+
+#[cfg(feature = "unicode-segmentation")]
+impl<'a> GraphemeCheckpoint<'a> {
+    pub fn new(input: &'a str) -> Self {
+        use unicode_segmentation::UnicodeSegmentation;
+        GraphemeCheckpoint {
+            inner: input.graphemes(true).peekable(),
+        }
+    }
+}
+
+#[cfg(feature = "unicode-segmentation")]
+impl<'a> Iterator for GraphemeCheckpoint<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+#[cfg(feature = "unicode-segmentation")]
+impl PeekableExt for GraphemeCheckpoint<'_> {
+    fn peek(&mut self) -> Option<&Self::Item> {
+        self.inner.peek()
+    }
+}
+
+/// Matches a single token tree whose textual form equals the given string, e.g.
+/// `TokenText("pub")` or `TokenText("=>")`.
+#[cfg(feature = "proc-macro2")]
+#[derive(Clone, Copy)]
+pub struct TokenText<'t>(pub &'t str);
+
+#[cfg(feature = "proc-macro2")]
+impl<'t> Satisfies<TokenText<'t>> for TokenTreeItem {
+    fn satisfies(&self, item: &TokenText<'t>) -> bool {
+        self.0.to_string() == item.0
+    }
+}
+
+#[cfg(feature = "proc-macro2")]
+impl<'a, 't, Reference> Pattern<'a, Reference> for TokenText<'t>
+where
+    Reference: Iterator<Item = TokenTreeItem> + PeekableExt,
+{
+    type Iter = core::iter::Once<TokenText<'t>>;
+    type Dest = ();
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::once(*self)
+    }
+
+    fn explain(&self) -> String {
+        format!("token `{}`", self.0)
+    }
+}
+
+/// Matches a single identifier token, capturing its text.
+#[cfg(feature = "proc-macro2")]
+pub struct IdentToken;
+
+#[cfg(feature = "proc-macro2")]
+pub const IDENT: IdentToken = IdentToken;
+
+#[cfg(feature = "proc-macro2")]
+impl Destination<TokenTreeItem> for String {}
+
+#[cfg(feature = "proc-macro2")]
+impl<'a, Reference> Pattern<'a, Reference> for IdentToken
+where
+    Reference: Iterator<Item = TokenTreeItem> + PeekableExt,
+{
+    type Iter = core::iter::Empty<TokenText<'static>>;
+    type Dest = String;
+
+    fn get_iter(&'a self) -> Self::Iter {
+        core::iter::empty()
+    }
+
+    fn explain(&self) -> String {
+        "identifier".to_string()
+    }
+
+    fn consume_with_dest(
+        &'a self,
+        reference: &mut Reference,
+        dest: Option<&RefCell<Self::Dest>>,
+    ) -> bool
+    where
+        Reference::Item: Satisfies<<Self::Iter as Iterator>::Item>,
+    {
+        let Some(TokenTreeItem(proc_macro2::TokenTree::Ident(ident))) = reference.peek() else {
+            return false;
+        };
+        let text = ident.to_string();
+        reference.next();
+        if let Some(dref) = dest {
+            *dref.borrow_mut() = text;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Checkpoint;
+
+    const WORD: Token<char, String> = Token {
+        predicate: |ch| ch.is_alphabetic(),
+        parser: |v| v.into_iter().collect(),
+        at_least: 1,
+        at_most: None,
+        skip_leading: Some(|ch| ch.is_whitespace()),
+        transform: None,
+    };
+
+    #[test]
+    fn skip_leading_advances_past_skipped_and_collected_items() {
+        let mut reference = Checkpoint::new("   ab  cd!".chars().peekable());
+        let dest = RefCell::new(String::new());
+        assert!(WORD.consume_with_dest(&mut reference, Some(&dest)));
+        assert_eq!(*dest.borrow(), "ab");
+        assert_eq!(reference.position(), 5);
+        assert!(WORD.consume_with_dest(&mut reference, Some(&dest)));
+        assert_eq!(*dest.borrow(), "cd");
+        assert_eq!(reference.position(), 9);
+        assert!(!WORD.consume(&mut reference));
+        assert_eq!(reference.collect::<String>(), "!");
+    }
+
+    #[test]
+    fn skip_leading_rolls_back_skipped_items_on_failure() {
+        let mut reference = Checkpoint::new("  !".chars().peekable());
+        assert!(!WORD.consume(&mut reference));
+        assert_eq!(reference.collect::<String>(), "  !");
+    }
+
+    #[test]
+    fn float_parses_points_signs_and_exponents() {
+        assert_eq!(crate::parse_str("3.14", &FLOAT), "3.14".parse::<f64>().ok());
+        assert_eq!(crate::parse_str("1e-10", &FLOAT), Some(1e-10));
+        assert_eq!(crate::parse_str("-0.5", &FLOAT), Some(-0.5));
+        assert_eq!(crate::parse_str::<_, f64>("abc", &FLOAT), None);
+        assert!(FLOAT.matches(&"1e-10"));
+        assert!(!FLOAT.matches(&"abc"));
+    }
+
+    #[test]
+    fn strict_float_needs_a_point() {
+        assert_eq!(
+            crate::parse_str("3.14", &STRICT_FLOAT),
+            "3.14".parse::<f64>().ok()
+        );
+        assert_eq!(crate::parse_str("-0.5", &STRICT_FLOAT), Some(-0.5));
+        assert_eq!(crate::parse_str::<_, f64>("1e-10", &STRICT_FLOAT), None);
+        assert_eq!(crate::parse_str::<_, f64>("abc", &STRICT_FLOAT), None);
+    }
+
+    #[test]
+    fn signed_int_needs_digits_after_the_sign() {
+        assert_eq!(crate::parse_str("-7", &SIGNED_INT), Some(-7i64));
+        assert_eq!(crate::parse_str::<_, i64>("-", &SIGNED_INT), None);
+        let mut reference = "-x".chars().peekable();
+        assert!(!SIGNED_INT.consume(&mut reference));
+        assert_eq!(reference.collect::<String>(), "-x");
+    }
+
+    #[test]
+    fn signed_int_saturates_out_of_range() {
+        assert_eq!(
+            crate::parse_str("-9223372036854775808", &SIGNED_INT),
+            Some(i64::MIN)
+        );
+        assert_eq!(
+            crate::parse_str("9223372036854775807", &SIGNED_INT),
+            Some(i64::MAX)
+        );
+        assert_eq!(
+            crate::parse_str("-99999999999999999999", &SIGNED_INT),
+            Some(i64::MIN)
+        );
+        assert_eq!(
+            crate::parse_str("99999999999999999999", &SIGNED_INT),
+            Some(i64::MAX)
+        );
+    }
+
+    #[test]
+    fn trimmed_consumes_surrounding_whitespace() {
+        let pattern = ALPHABETIC.trimmed();
+        let mut reference = " \n word  !".chars().peekable();
+        let dest = RefCell::new(Default::default());
+        assert!(pattern.consume_with_dest(&mut reference, Some(&dest)));
+        assert_eq!(dest.into_inner().1, "word");
+        assert_eq!(reference.collect::<String>(), "!");
+        assert!(crate::trim!(ALPHABETIC).matches(&"  word "));
+    }
+}
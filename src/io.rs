@@ -0,0 +1,150 @@
+use core::slice;
+use std::cell::RefCell;
+use std::io::{BufRead, Cursor, ErrorKind, Read, Write};
+use std::iter::Peekable;
+
+use crate::base::{Checkpoint, Collector, Destination, Iterable};
+
+/// An iterator over the bytes of a reader, read one at a time. Read errors end the
+/// iteration. Wrap it as `Checkpoint::new(ReadIter(reader).peekable())` to match on it.
+#[derive(Clone)]
+pub struct ReadIter<R>(pub R);
+
+impl<R> Iterator for ReadIter<R>
+where
+    R: Read,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut byte = [0u8; 1];
+        match self.0.read(&mut byte) {
+            Ok(1) => Some(byte[0]),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the bytes a checkpoint over `u8` items has not consumed yet, so it can be handed
+/// to anything expecting a reader, e.g. `BufReader::new(checkpoint)`.
+impl<I> Read for Checkpoint<I>
+where
+    I: Iterator<Item = u8>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut filled = 0;
+        for slot in buf.iter_mut() {
+            match self.next() {
+                Some(byte) => *slot = byte,
+                None => break,
+            }
+            filled += 1;
+        }
+        Ok(filled)
+    }
+}
+
+/// Buffers up to the next newline at a time, using the checkpoint's own lookahead buffer.
+impl<I> BufRead for Checkpoint<I>
+where
+    I: Iterator<Item = u8>,
+{
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        Ok(self.fill_front_until(|byte| *byte == b'\n'))
+    }
+
+    fn consume(&mut self, amt: usize) {
+        for _ in 0..amt {
+            self.next();
+        }
+    }
+}
+
+/// Matches the bytes from the cursor's current position onwards, the same way `&[u8]`
+/// is matched, so byte-slice patterns apply unchanged.
+impl<'a> Iterable<'a> for Cursor<&'a [u8]> {
+    type Iter = Peekable<slice::Iter<'a, u8>>;
+    fn get_iter(&'a self) -> Self::Iter {
+        let bytes: &'a [u8] = self.get_ref();
+        let start = usize::try_from(self.position()).map_or(bytes.len(), |p| p.min(bytes.len()));
+        bytes[start..].iter().peekable()
+    }
+}
+
+/// A destination that writes captured text or bytes straight to a `Write` sink instead
+/// of buffering them, e.g. `To(ALPHABETIC, &Dest::from(IoWrapper::new(&file)))`. `To`
+/// needs its destination to be `Clone`, so pass a shared handle such as `&File`.
+/// The first write error stops all further writes and is kept for `error`.
+#[derive(Clone, Debug)]
+pub struct IoWrapper<W> {
+    sink: W,
+    error: Option<ErrorKind>,
+}
+
+impl<W> IoWrapper<W>
+where
+    W: Write,
+{
+    pub fn new(sink: W) -> Self {
+        IoWrapper { sink, error: None }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.sink
+    }
+
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+
+    /// The kind of the first write error, if any write has failed.
+    pub fn error(&self) -> Option<ErrorKind> {
+        self.error
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        if self.error.is_none()
+            && let Err(e) = self.sink.write_all(bytes)
+        {
+            self.error = Some(e.kind());
+        }
+    }
+}
+
+impl<W> Destination<char> for IoWrapper<W>
+where
+    W: Write,
+{
+    fn pickup(&mut self, item: char) {
+        self.write(item.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+}
+
+impl<W> Destination<u8> for IoWrapper<W>
+where
+    W: Write,
+{
+    fn pickup(&mut self, item: u8) {
+        self.write(&[item]);
+    }
+}
+
+impl<W> Destination<&u8> for IoWrapper<W>
+where
+    W: Write,
+{
+    fn pickup(&mut self, item: &u8) {
+        self.write(&[*item]);
+    }
+}
+
+/// Captures such as `String` or `Vec<u8>` are written out whole when `To` commits them.
+impl<Inner, Item, W> Collector<Inner, Item> for IoWrapper<W>
+where
+    Inner: AsRef<[u8]>,
+    W: Write,
+{
+    fn commit(out: &RefCell<Self>, captured: Inner) {
+        out.borrow_mut().write(captured.as_ref());
+    }
+}
@@ -1,10 +1,77 @@
 pub mod base;
 pub mod dest;
 pub mod exts;
+#[cfg(feature = "io")]
+pub mod io;
+pub mod tokenize;
+pub mod utf16;
+pub mod utils;
 
 // Re-exports to make core pattern types available at crate root for macro expansions
-pub use base::{Checkpoint, Or, Pattern, Sep, Sep1, To};
-pub use match_string_macros::matches;
+pub use base::{
+    AndThen, AnyOf, CaseInsensitive, Checkpoint, CheckpointStats, Discard, END, End, Followed,
+    FormatPattern, FromPattern, Inspect, Lazy, Longest, Lookahead, NegLookahead, NoneOf, Not, Opt,
+    Or, Parsed, Pattern, PatternFn, Preceded, RepeatBetween, RepeatExact, RepeatMax, RepeatMin,
+    RepeatUntil, Sep, Sep1, SepN, SepTerminated, SepWith, To, Trace, Unordered, UnorderedSeq,
+    WORD_BOUNDARY, WordBoundary, Zip,
+};
+#[cfg(feature = "bench")]
+pub use match_string_macros::bench_pattern;
+pub use match_string_macros::{
+    Captures, Pattern, alt, compile_pattern, match_or, matches, matches_bind, or, or_any, seq, trim,
+};
+
+/// Match `pattern` at the start of `input` and convert its capture, ignoring any input
+/// left over. For example, `parse_str::<_, usize>("42abc", &NUM)` is `Some(42)`.
+pub fn parse_str<'a, 's, P, T>(input: &'s str, pattern: &'a P) -> Option<T>
+where
+    P: crate::base::Pattern<'a, Checkpoint<std::iter::Peekable<std::str::Chars<'s>>>>,
+    P::Dest: crate::base::Destination<char> + Default + Into<T>,
+    char: crate::base::Satisfies<<P::Iter as Iterator>::Item>,
+{
+    let mut reference = Checkpoint::new(input.chars().peekable());
+    __consume_field(pattern, &mut reference).map(Into::into)
+}
+
+/// Like `parse_str`, but fails unless `pattern` consumes the whole input.
+pub fn parse_str_exact<'a, 's, P, T>(input: &'s str, pattern: &'a P) -> Option<T>
+where
+    P: crate::base::Pattern<'a, Checkpoint<std::iter::Peekable<std::str::Chars<'s>>>>,
+    P::Dest: crate::base::Destination<char> + Default + Into<T>,
+    char: crate::base::Satisfies<<P::Iter as Iterator>::Item>,
+{
+    let mut reference = Checkpoint::new(input.chars().peekable());
+    let captured = __consume_field(pattern, &mut reference)?;
+    crate::base::PeekableExt::peek(&mut reference)
+        .is_none()
+        .then(|| captured.into())
+}
+
+/// Internal helper used by the proc-macros to check each identifier term on its own, so
+/// that a term which is not a pattern is reported at the term itself.
+pub fn __assert_pattern<'a, 's, P, R>(_pattern: &'a P, _reference: &'s R)
+where
+    R: crate::base::Iterable<'s> + 's,
+    P: crate::base::Pattern<'a, R::Iter>,
+{
+}
+
+/// Internal helper used by `#[derive(Pattern)]` and `#[derive(Captures)]`: match one
+/// field's pattern and return its capture.
+pub fn __consume_field<'a, 's, P>(
+    pattern: &'a P,
+    reference: &mut Checkpoint<std::iter::Peekable<std::str::Chars<'s>>>,
+) -> Option<P::Dest>
+where
+    P: crate::base::Pattern<'a, Checkpoint<std::iter::Peekable<std::str::Chars<'s>>>>,
+    P::Dest: crate::base::Destination<char> + Default,
+    char: crate::base::Satisfies<<P::Iter as Iterator>::Item>,
+{
+    let dest = std::cell::RefCell::new(P::Dest::default());
+    pattern
+        .consume_with_dest(reference, Some(&dest))
+        .then(|| dest.into_inner())
+}
 
 /// Internal helper used by the proc-macro to call the `Pattern::matches` method
 /// with the correct trait bounds so method resolution succeeds in macro expansions.
@@ -18,3 +85,22 @@ where
 {
     <P as crate::base::Pattern<'a, Reference>>::matches(pat, reference)
 }
+
+/// Internal helper used by `matches_bind!`: match the whole reference and return the
+/// pattern's captured `Dest` on success.
+pub fn __matches_dest<'a, 's, P, Reference, R>(pat: &'a P, reference: &'s R) -> Option<P::Dest>
+where
+    P: crate::base::Pattern<'a, Reference>,
+    R: crate::base::Iterable<'s, Iter = Reference> + 's,
+    Reference: crate::base::PeekableExt,
+    P::Dest: crate::base::Destination<Reference::Item> + Default,
+    Reference::Item: crate::base::Satisfies<<P::Iter as Iterator>::Item>,
+{
+    let mut iter = reference.get_iter();
+    let dest = std::cell::RefCell::new(P::Dest::default());
+    if pat.consume_with_dest(&mut iter, Some(&dest)) && iter.peek().is_none() {
+        Some(dest.into_inner())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,79 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::base::{Checkpoint, Destination, Pattern, Satisfies};
+
+type StrIter<'s> = Checkpoint<Peekable<Chars<'s>>>;
+
+/// An object-safe view of a pattern over string input, so patterns of different types
+/// can be stored side by side.
+pub trait DynPattern {
+    /// Number of chars the pattern matches at the start of `input`, if it matches.
+    fn match_len(&self, input: &str) -> Option<usize>;
+}
+
+impl<P> DynPattern for P
+where
+    P: for<'a, 's> Pattern<'a, StrIter<'s>>,
+    for<'a, 's> <P as Pattern<'a, StrIter<'s>>>::Dest: Destination<char>,
+    for<'a, 's> char: Satisfies<<<P as Pattern<'a, StrIter<'s>>>::Iter as Iterator>::Item>,
+{
+    fn match_len(&self, input: &str) -> Option<usize> {
+        self.matches_prefix(&input)
+    }
+}
+
+type Entry<T> = (Box<dyn DynPattern>, fn(&str) -> T);
+
+/// Splits a string into tokens using a list of patterns, tried in the order they were added.
+pub struct Tokenizer<T> {
+    entries: Vec<Entry<T>>,
+}
+
+impl<T> Default for Tokenizer<T> {
+    fn default() -> Self {
+        Tokenizer {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T> Tokenizer<T> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Register a pattern and the constructor applied to the text it matches.
+    pub fn add<P>(mut self, pattern: P, constructor: fn(&str) -> T) -> Self
+    where
+        P: DynPattern + 'static,
+    {
+        self.entries.push((Box::new(pattern), constructor));
+        self
+    }
+
+    /// Tokenize `input`. At each position the first pattern that matches a non-empty
+    /// prefix wins; a character no pattern matches is skipped.
+    pub fn tokenize(&self, input: &str) -> Vec<T> {
+        let mut tokens = Vec::new();
+        let mut rest = input;
+        while let Some(first) = rest.chars().next() {
+            let matched = self.entries.iter().find_map(|(pattern, constructor)| {
+                match pattern.match_len(rest) {
+                    Some(len) if len > 0 => Some((len, constructor)),
+                    _ => None,
+                }
+            });
+            let advance = match matched {
+                Some((len, constructor)) => {
+                    let end = rest.char_indices().nth(len).map_or(rest.len(), |(i, _)| i);
+                    tokens.push(constructor(&rest[..end]));
+                    end
+                }
+                None => first.len_utf8(),
+            };
+            rest = &rest[advance..];
+        }
+        tokens
+    }
+}
@@ -0,0 +1,66 @@
+use core::slice;
+use std::iter::Peekable;
+
+use crate::base::{Iterable, PeekableExt};
+
+/// An iterator over UTF-16 code units that decodes surrogate pairs on the fly, yielding
+/// `char`s. Unpaired surrogates decode to `char::REPLACEMENT_CHARACTER`.
+#[derive(Clone)]
+pub struct Utf16Checkpoint<'a> {
+    inner: Peekable<slice::Iter<'a, u16>>,
+    peeked: Option<char>,
+}
+
+impl<'a> Utf16Checkpoint<'a> {
+    pub fn new(units: &'a [u16]) -> Self {
+        Utf16Checkpoint {
+            inner: units.iter().peekable(),
+            peeked: None,
+        }
+    }
+
+    fn decode_next(&mut self) -> Option<char> {
+        let unit = u32::from(*self.inner.next()?);
+        let ch = match unit {
+            0xD800..=0xDBFF => match self.inner.peek() {
+                Some(&&low) if (0xDC00..=0xDFFF).contains(&low) => {
+                    self.inner.next();
+                    let high = (unit - 0xD800) << 10;
+                    char::from_u32(0x10000 + high + (u32::from(low) - 0xDC00))
+                }
+                _ => None,
+            },
+            _ => char::from_u32(unit),
+        };
+        Some(ch.unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+}
+
+impl Iterator for Utf16Checkpoint<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.peeked.take().or_else(|| self.decode_next())
+    }
+}
+
+impl PeekableExt for Utf16Checkpoint<'_> {
+    fn peek(&mut self) -> Option<&Self::Item> {
+        if self.peeked.is_none() {
+            self.peeked = self.decode_next();
+        }
+        self.peeked.as_ref()
+    }
+}
+
+/// UTF-16 encoded input. `&[u16]` already iterates as raw code units, so wrap the slice
+/// to match it as text: `"hi".matches(&Utf16(&units))`.
+#[derive(Clone, Copy)]
+pub struct Utf16<'a>(pub &'a [u16]);
+
+impl<'a> Iterable<'a> for Utf16<'_> {
+    type Iter = Utf16Checkpoint<'a>;
+    fn get_iter(&'a self) -> Self::Iter {
+        Utf16Checkpoint::new(self.0)
+    }
+}
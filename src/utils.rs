@@ -0,0 +1,186 @@
+/// The outcome of a single sub-pattern in a detailed match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchResult {
+    /// The sub-pattern matched.
+    Matched,
+    /// The sub-pattern was tried and failed.
+    Error,
+    /// The sub-pattern was not tried because an earlier one failed.
+    Skipped,
+}
+
+impl From<bool> for MatchResult {
+    fn from(matched: bool) -> Self {
+        if matched {
+            MatchResult::Matched
+        } else {
+            MatchResult::Error
+        }
+    }
+}
+
+/// The empty result list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HNil;
+
+/// A result list made of a head result and a tail list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HCons<T>(pub MatchResult, pub T);
+
+/// A heterogeneous list of per-sub-pattern match results.
+pub trait ResultHList {
+    /// Number of results in the list.
+    const LEN: usize;
+    /// Whether every result in the list is `Matched`.
+    fn is_matched(&self) -> bool;
+    /// Append another list after this one.
+    fn append<T: ResultHList>(self, other: T) -> impl ResultHList;
+    /// Push the results onto `out`, in order.
+    fn push_into(&self, out: &mut Vec<MatchResult>);
+    /// Collect the results into a `Vec`, in order.
+    fn to_vec(&self) -> Vec<MatchResult> {
+        let mut out = Vec::with_capacity(Self::LEN);
+        self.push_into(&mut out);
+        out
+    }
+}
+
+impl ResultHList for HNil {
+    const LEN: usize = 0;
+
+    fn is_matched(&self) -> bool {
+        true
+    }
+
+    fn append<T: ResultHList>(self, other: T) -> impl ResultHList {
+        other
+    }
+
+    fn push_into(&self, _out: &mut Vec<MatchResult>) {}
+}
+
+impl<Tail> ResultHList for HCons<Tail>
+where
+    Tail: ResultHList,
+{
+    const LEN: usize = 1 + Tail::LEN;
+
+    fn is_matched(&self) -> bool {
+        self.0 == MatchResult::Matched && self.1.is_matched()
+    }
+
+    fn append<T: ResultHList>(self, other: T) -> impl ResultHList {
+        HCons(self.0, self.1.append(other))
+    }
+
+    fn push_into(&self, out: &mut Vec<MatchResult>) {
+        out.push(self.0);
+        self.1.push_into(out);
+    }
+}
+
+/// What went wrong where a pattern stopped matching.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind<T> {
+    /// The input ended before the pattern was complete.
+    UnexpectedEnd,
+    /// The pattern could not accept this item.
+    Unexpected(T),
+    /// A repetition matched fewer times than it needs to.
+    MinOccurrences { expected: usize, got: usize },
+}
+
+/// A structured pattern failure, returned by `Pattern::try_consume_err`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError<T> {
+    pub kind: ErrorKind<T>,
+    /// Descriptions of the patterns that were being matched, innermost first.
+    pub context: Vec<String>,
+    /// How many items past the start of the attempt the failure happened.
+    pub position: usize,
+}
+
+impl<T> PatternError<T> {
+    /// An error for the item `rest` is at, or for the end of input if it is empty.
+    pub fn at<I>(rest: &mut I, position: usize) -> Self
+    where
+        I: crate::base::PeekableExt<Item = T>,
+        T: Clone,
+    {
+        let kind = match rest.peek() {
+            Some(item) => ErrorKind::Unexpected(item.clone()),
+            None => ErrorKind::UnexpectedEnd,
+        };
+        PatternError {
+            kind,
+            context: Vec::new(),
+            position,
+        }
+    }
+
+    /// Record that the failure happened while matching the pattern described by `context`.
+    pub fn within(mut self, context: String) -> Self {
+        self.context.push(context);
+        self
+    }
+
+    /// Shift the position by `offset` items, for a failure in a later part of a sequence.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.position += offset;
+        self
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Display for PatternError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ErrorKind::UnexpectedEnd => write!(f, "unexpected end of input")?,
+            ErrorKind::Unexpected(item) => write!(f, "unexpected {:?}", item)?,
+            ErrorKind::MinOccurrences { expected, got } => {
+                write!(f, "expected at least {} repetitions, got {}", expected, got)?
+            }
+        }
+        write!(f, " at position {}", self.position)?;
+        if let Some(outer) = self.context.last() {
+            write!(f, " while matching {}", outer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: std::fmt::Debug> std::error::Error for PatternError<T> {}
+
+/// A tree describing one match attempt, returned by `Pattern::debug_match`. Combinators
+/// report each sub-pattern they tried in `sub_reports`, in the order they were tried.
+#[cfg(feature = "debug-report")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugMatchReport {
+    /// The pattern's `explain` text.
+    pub pattern_name: String,
+    pub succeeded: bool,
+    /// The `Debug` form of each item the attempt read, including those read before a
+    /// failure, which a real match would not have consumed.
+    pub consumed_items: Vec<String>,
+    pub sub_reports: Vec<DebugMatchReport>,
+}
+
+#[cfg(feature = "debug-report")]
+impl DebugMatchReport {
+    /// The report of a sequence, which read whatever its parts read, in order.
+    pub(crate) fn sequence(
+        pattern_name: String,
+        succeeded: bool,
+        sub_reports: Vec<DebugMatchReport>,
+    ) -> Self {
+        let consumed_items = sub_reports
+            .iter()
+            .flat_map(|report| report.consumed_items.iter().cloned())
+            .collect();
+        DebugMatchReport {
+            pattern_name,
+            succeeded,
+            consumed_items,
+            sub_reports,
+        }
+    }
+}